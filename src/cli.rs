@@ -1,5 +1,9 @@
 use clap::ArgAction;
 use clap::{Parser, Subcommand};
+use rfortune::export::Format;
+use rfortune::selector::Policy;
+use rfortune::style::{Character, Style};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -15,22 +19,88 @@ fortune files, and cache using subcommands:\n\n  \
 • `config init`   Create a configuration file with default options.\n  \
 • `config edit`   Edit the configuration file using the system or a chosen editor.\n  \
 • `file init`     Create a sample default fortune file (rfortune.dat).\n  \
+• `file edit`     Edit a fortune file and check it when the editor is closed.\n  \
 • `cache clear`   Remove all cached last-used fortunes.\n\n\
 This makes it easy to test, customize and extend your fortune collections \
 while preserving the spirit of the original UNIX command.",
     after_help = "EXAMPLES:\n  rfortune\n      Print a random fortune from the default file (rfortune.dat).\n\n  \
   rfortune --file ~/fortunes/misc\n      Print a random fortune from the file ~/fortunes/misc.\n\n  \
+  rfortune --template \"{text}{?author} ({author}){/author}\"\n      Print the quote and its author on a single line.\n\n  \
+  rfortune --width 60 --indent 4\n      Reflow the quote to 60 columns, indented by four spaces.\n\n  \
+  rfortune --style bubble --character ferris\n      Let Ferris the crab say the quote, cowsay-style.\n\n  \
   rfortune config init\n      Create a default configuration file in the user data directory.\n\n  \
   rfortune config edit\n      Open the configuration file in your default system editor.\n\n  \
   rfortune config edit --editor vi\n      Open the configuration file with a specific editor.\n\n  \
+  rfortune file edit ~/fortunes/dev.fort --editor \"code --wait\"\n      Edit a fortune file and check it when the editor is closed.\n\n  \
+  rfortune config set fortune_files ~/fortunes/dev --append\n      Add a fortune file to the configuration without opening an editor.\n\n  \
+  rfortune --profile greeting\n      Use the sources, filters and output settings of the `greeting` profile.\n\n  \
+  rfortune config migrate --dry-run\n      Show how an old configuration file would be upgraded.\n\n  \
   rfortune file init\n      Create a sample fortune file (rfortune.dat) in the user data directory.\n\n  \
-  rfortune cache clear\n      Remove all cached last-used fortunes."
+  rfortune cache clear\n      Remove all cached last-used fortunes.\n\n  \
+  rfortune export team.fort --format html -o team.html\n      Publish a whole collection as an HTML page."
 )]
 pub struct Cli {
-    /// Fortune file to use instead of the default (rfortune.dat)
+    /// Fortune file to use instead of the default (rfortune.dat); `-` reads standard input
     #[arg(long = "file", value_name = "FILE", num_args = 1.., action = ArgAction::Append)]
     pub files: Option<Vec<String>>,
 
+    /// Render the quote with a custom template, e.g. "{text}{?author}\n  -- {author}{/author}".
+    /// Placeholders: {text}, {author}, {title}, {source}, {index}, {quote}
+    #[arg(long, value_name = "TEMPLATE")]
+    pub template: Option<String>,
+
+    /// Wrap lines at N columns (defaults to the terminal width; 0 disables wrapping)
+    #[arg(long, value_name = "N")]
+    pub width: Option<usize>,
+
+    /// Indent every line by N spaces
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub indent: usize,
+
+    /// Center every line within the output width
+    #[arg(long)]
+    pub center: bool,
+
+    /// Draw a frame around the quote: plain, box, ascii or bubble
+    #[arg(long, value_name = "STYLE")]
+    pub style: Option<Style>,
+
+    /// Character speaking the quote with `--style bubble`: cow, tux or ferris
+    #[arg(long, value_name = "NAME")]
+    pub character: Option<Character>,
+
+    /// Output format: text, markdown or html
+    #[arg(long, value_name = "FORMAT")]
+    pub format: Option<Format>,
+
+    /// How the quote is chosen: weighted, uniform, shuffle (every quote once before repeating) or daily
+    #[arg(long, value_name = "POLICY")]
+    pub selection: Option<Policy>,
+
+    /// Wait after printing, for a time proportional to the quote length (only on a terminal)
+    #[arg(short, long)]
+    pub wait: bool,
+
+    /// Show the file the fortune comes from and its position in that file
+    #[arg(short = 'c', long)]
+    pub show_source: bool,
+
+    /// Use this configuration file instead of the system, user and project ones
+    #[arg(long = "config", value_name = "PATH", global = true)]
+    pub config_path: Option<PathBuf>,
+
+    /// Never create the configuration directory or files, even if they are missing
+    #[arg(long, global = true, conflicts_with = "yes")]
+    pub no_init: bool,
+
+    /// Create the configuration directory and files without asking, if they are missing
+    #[arg(short = 'y', long, global = true)]
+    pub yes: bool,
+
+    /// Use a named profile from the configuration (overrides RFORTUNE_PROFILE and default_profile)
+    #[arg(long, value_name = "NAME", global = true)]
+    pub profile: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -52,6 +122,20 @@ pub enum Commands {
         #[command(subcommand)]
         action: CacheAction,
     },
+    /// Export whole fortune files as Markdown, HTML or plain fortune text
+    Export {
+        /// Fortune files to export (defaults to the configured sources)
+        #[arg(value_name = "FILE")]
+        files: Vec<String>,
+
+        /// Export format: markdown, html or text
+        #[arg(long, value_name = "FORMAT", default_value = "markdown")]
+        format: Format,
+
+        /// Write the result to a file instead of stdout
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -61,16 +145,67 @@ pub enum ConfigAction {
 
     /// Edit the configuration file with the system or custom editor
     Edit {
-        /// Specify a custom editor name or path
+        /// Specify a custom editor command, e.g. "code --wait" or "emacsclient -t"
         #[arg(short, long)]
         editor: Option<String>,
     },
+
+    /// Print the effective configuration and where each value comes from
+    Show,
+
+    /// Print the effective value of a single key
+    Get {
+        /// Configuration key, e.g. `style` or `fortune_files`
+        key: String,
+    },
+
+    /// Set a key in the configuration file (the user file, or the one given with --config)
+    Set {
+        /// Configuration key, e.g. `style` or `fortune_files`
+        key: String,
+
+        /// New value; list keys such as `fortune_files` accept several values
+        #[arg(required = true, num_args = 1..)]
+        values: Vec<String>,
+
+        /// Append the values to a list key instead of replacing it
+        #[arg(short, long)]
+        append: bool,
+    },
+
+    /// Check the configuration for syntax errors, unknown keys, wrong types and missing files
+    Validate,
+
+    /// Upgrade the configuration file to the current schema version (a backup is kept)
+    Migrate {
+        /// Show the changes without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Print the location of the configuration file
+    Path {
+        /// List every configuration layer and whether it exists
+        #[arg(long)]
+        all: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
 pub enum FileAction {
     /// Create a sample default fortune file (rfortune.dat)
     Init,
+
+    /// Edit a fortune file (the first configured source by default) and check it afterwards
+    Edit {
+        /// Fortune file to edit
+        #[arg(value_name = "FILE")]
+        file: Option<PathBuf>,
+
+        /// Specify a custom editor command, e.g. "code --wait" or "emacsclient -t"
+        #[arg(short, long)]
+        editor: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -1,30 +1,383 @@
 use crate::{config, utils};
+use rfortune::config::Config;
+use rfortune::context::AppContext;
+use rfortune::editor::{self, AfterError, EditOutcome};
+use rfortune::export::{self, Format};
+use rfortune::layers::{self, ConfigLayer, ConfigSource};
+use rfortune::loader::{FortuneFile, ParseOptions};
 use rfortune::log::ConsoleLog;
+use rfortune::migrate::{self, MigrationReport};
+use rfortune::validate::{self, Issue, Severity};
+use rfortune::{Error, Result};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-pub fn run_config_init() {
+pub fn run_config_init(ctx: &AppContext) -> Result<()> {
     ConsoleLog::info("Initializing configuration file...");
-    if let Err(e) = config::init_config_file() {
-        ConsoleLog::ko(format!("Error initializing config: {e}"));
+    if config::init_config_file(ctx)? {
+        ConsoleLog::ok("Configuration file successfully created.");
+    } else {
+        ConsoleLog::info("Configuration file already exists, skipping.");
     }
+    Ok(())
 }
 
-pub fn run_file_init() {
+pub fn run_file_init(ctx: &AppContext) -> Result<()> {
     ConsoleLog::info("Initializing default fortune file...");
-    if let Err(e) = config::init_default_file() {
-        ConsoleLog::ko(format!("Error initializing fortune file: {e}"));
-    }
+    config::init_default_file(ctx)
 }
 
-pub fn run_cache_clear() {
+pub fn run_cache_clear(ctx: &AppContext) -> Result<()> {
     ConsoleLog::info("Clearing cache directory...");
-    if let Err(e) = utils::clear_cache_dir() {
-        ConsoleLog::ko(format!("Error clearing cache: {e}"));
+    if utils::clear_cache_dir(ctx)? {
+        ConsoleLog::ok(format!(
+            "Cache directory cleared: {}",
+            ctx.paths.cache_dir.display()
+        ));
+    } else {
+        ConsoleLog::info("Cache directory is already empty.");
     }
+    Ok(())
 }
 
-pub(crate) fn run_config_edit(editor: Option<String>) {
-    ConsoleLog::info("Clearing cache directory...");
-    if let Err(e) = config::run_config_edit(editor) {
-        ConsoleLog::ko(format!("Error opening config in editor: {e}"));
+/// Dopo una modifica non valida: mostra gli errori e, su un terminale, chiede cosa fare
+fn after_edit_error(errors: &[String]) -> Result<AfterError> {
+    for e in errors {
+        ConsoleLog::ko(e);
+    }
+    if !atty::is(atty::Stream::Stdin) {
+        return Ok(AfterError::Restore);
+    }
+
+    print!("What now? (e)dit again, (k)eep anyway, (r)estore previous version [E/k/r]: ");
+    io::stdout().flush().map_err(|e| Error::io("<stdout>", e))?;
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .map_err(|e| Error::io("<stdin>", e))?;
+    Ok(match input.trim().to_lowercase().as_str() {
+        "k" | "keep" => AfterError::Keep,
+        "r" | "restore" => AfterError::Restore,
+        _ => AfterError::Edit,
+    })
+}
+
+pub(crate) fn run_config_edit(
+    ctx: &AppContext,
+    editor: Option<String>,
+    explicit: Option<&Path>,
+) -> Result<()> {
+    let path = config::target_config_path(ctx, explicit);
+
+    // Se non esiste, crealo
+    if !path.exists() && explicit.is_none() {
+        ConsoleLog::warn("Configuration file not found. Creating a new one...");
+        config::init_config_file(ctx)?;
+    }
+
+    let editor = editor::resolve_editor(editor);
+    let origin = path.display().to_string();
+    let check = |bytes: &[u8]| {
+        let content = std::str::from_utf8(bytes)
+            .map_err(|e| vec![format!("{origin}: not valid UTF-8: {e}")])?;
+        let issues = validate::validate_content(&origin, content);
+        for warning in issues.iter().filter(|i| i.severity == Severity::Warning) {
+            ConsoleLog::warn(warning);
+        }
+        let errors: Vec<String> = issues
+            .iter()
+            .filter(|i| i.is_error())
+            .map(ToString::to_string)
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    };
+
+    if editor::edit_validated(ctx, &editor, &path, check, after_edit_error)? == EditOutcome::Valid {
+        ConsoleLog::ok("Configuration file saved and validated.");
+    }
+    Ok(())
+}
+
+pub fn run_file_edit(
+    ctx: &AppContext,
+    file: Option<PathBuf>,
+    editor: Option<String>,
+    config: &Config,
+) -> Result<()> {
+    // Senza argomento: la prima sorgente configurata, o il file di default
+    let path = file
+        .or_else(|| {
+            utils::resolve_fortune_sources(None, config)
+                .first()
+                .map(PathBuf::from)
+        })
+        .unwrap_or_else(|| ctx.paths.default_fortune_file());
+
+    if path == ctx.paths.default_fortune_file() {
+        config::init_default_file(ctx)?;
+    }
+
+    let editor = editor::resolve_editor(editor);
+    let parse = config.parse_options()?;
+    let check = |bytes: &[u8]| {
+        FortuneFile::read_with(bytes, &path, &parse)
+            .map(|_| ())
+            .map_err(|e| vec![e.to_string()])
+    };
+    let outcome = editor::edit_validated(ctx, &editor, &path, check, after_edit_error)?;
+
+    if outcome == EditOutcome::Valid {
+        ConsoleLog::ok(format!("{} saved and checked.", path.display()));
+    }
+    Ok(())
+}
+
+pub fn run_export(
+    sources: &[String],
+    format: Format,
+    output: Option<PathBuf>,
+    parse: &ParseOptions,
+) -> Result<()> {
+    if sources.is_empty() {
+        return Err(Error::Config(
+            "No fortune sources configured or provided.".to_string(),
+        ));
+    }
+
+    let paths: Vec<&Path> = sources.iter().map(Path::new).collect();
+    let document = export::export_files_with(&paths, format, parse)?;
+
+    match output {
+        Some(path) => {
+            fs::write(&path, document).map_err(|e| Error::io(&path, e))?;
+            ConsoleLog::ok(format!("Exported {format} to {}", path.display()));
+        }
+        None => print!("{document}"),
+    }
+    Ok(())
+}
+
+/// Livelli attivi, compreso il profilo selezionato; un profilo sconosciuto è un errore
+fn active_layers(
+    ctx: &AppContext,
+    explicit: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<Vec<ConfigLayer>> {
+    let mut layers = layers::config_layers(ctx, explicit);
+    layers::apply_profile(&mut layers, profile)?;
+    Ok(layers)
+}
+
+pub fn run_config_show(
+    ctx: &AppContext,
+    explicit: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<()> {
+    let layers = active_layers(ctx, explicit, profile)?;
+    if let Some(active) = layers.iter().find_map(|l| match &l.source {
+        ConfigSource::Profile(name) => Some(name),
+        _ => None,
+    }) {
+        ConsoleLog::info(format!("Active profile: {active}"));
+    }
+
+    let width = layers::CONFIG_KEYS
+        .iter()
+        .map(|(k, _)| k.len())
+        .max()
+        .unwrap_or(0);
+
+    for (key, entry) in layers::effective_values(&layers) {
+        match entry {
+            Some((value, source)) => println!(
+                "{key:<width$} = {}  # {source}",
+                config::format_value(&value)
+            ),
+            None => println!("{key:<width$} = ~  # not set"),
+        }
+    }
+    Ok(())
+}
+
+pub fn run_config_get(
+    ctx: &AppContext,
+    explicit: Option<&Path>,
+    profile: Option<&str>,
+    key: &str,
+) -> Result<()> {
+    if layers::key_kind(key).is_none() {
+        return Err(Error::Config(format!("Unknown configuration key '{key}'.")));
+    }
+
+    let layers = active_layers(ctx, explicit, profile)?;
+    let value = layers::effective_values(&layers)
+        .into_iter()
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, entry)| entry);
+
+    match value {
+        // Una riga per elemento, comodo negli script
+        Some((serde_yaml::Value::Sequence(seq), _)) => {
+            for item in &seq {
+                println!("{}", config::format_value(item));
+            }
+        }
+        // Una riga `CHIAVE=VALORE` per voce, lo stesso formato accettato da `config set`
+        Some((serde_yaml::Value::Mapping(map), _)) => {
+            for (k, v) in &map {
+                println!("{}={}", config::format_value(k), config::format_value(v));
+            }
+        }
+        Some((value, _)) => println!("{}", config::format_value(&value)),
+        None => {}
+    }
+    Ok(())
+}
+
+pub fn run_config_set(
+    ctx: &AppContext,
+    explicit: Option<&Path>,
+    key: &str,
+    values: &[String],
+    append: bool,
+) -> Result<()> {
+    let path = config::target_config_path(ctx, explicit);
+
+    // Un file di una versione precedente viene prima migrato, con la sua copia di sicurezza
+    if let Some(report) = migrate::migrate_file(&path, false)? {
+        report_migration(&report);
+    }
+
+    let mut cfg = config::load_config_file(&path)?;
+    cfg.set_value(key, values, append)?;
+    cfg.save_to(&path)?;
+
+    ConsoleLog::ok(format!("'{key}' updated in {}", path.display()));
+    Ok(())
+}
+
+pub fn run_config_path(ctx: &AppContext, explicit: Option<&Path>, all: bool) -> Result<()> {
+    if !all {
+        println!("{}", config::target_config_path(ctx, explicit).display());
+        return Ok(());
+    }
+
+    let mut candidates = match explicit {
+        Some(p) => vec![ConfigSource::Explicit(p.to_path_buf())],
+        None => vec![
+            ConfigSource::System(layers::system_config_path()),
+            ConfigSource::User(ctx.paths.config_file()),
+        ],
+    };
+    if explicit.is_none()
+        && let Some(project) = std::env::current_dir()
+            .ok()
+            .and_then(|cwd| layers::find_project_config(&cwd))
+    {
+        candidates.push(ConfigSource::Project(project));
+    }
+
+    for source in candidates {
+        let exists = source.path().is_some_and(Path::exists);
+        println!("{source}{}", if exists { "" } else { "  (not found)" });
+    }
+    Ok(())
+}
+
+/// Stampa i problemi trovati; ritorna `true` se non ci sono errori bloccanti
+pub fn report_issues(issues: &[Issue]) -> bool {
+    for issue in issues {
+        match issue.severity {
+            Severity::Error => ConsoleLog::ko(issue),
+            Severity::Warning => ConsoleLog::warn(issue),
+            Severity::Info => ConsoleLog::info(issue),
+        }
+    }
+    !issues.iter().any(Issue::is_error)
+}
+
+pub fn run_config_validate(
+    ctx: &AppContext,
+    explicit: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<()> {
+    let issues = validate::validate_all(ctx, explicit, profile);
+
+    if issues.is_empty() {
+        ConsoleLog::ok("Configuration is valid.");
+        return Ok(());
+    }
+
+    let count = |s: Severity| issues.iter().filter(|i| i.severity == s).count();
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    report_issues(&issues);
+    if errors > 0 {
+        return Err(Error::Config(format!(
+            "Configuration has {errors} error(s) and {warnings} warning(s)."
+        )));
+    } else if warnings > 0 {
+        ConsoleLog::warn(format!(
+            "Configuration is usable, with {warnings} warning(s)."
+        ));
+    } else {
+        ConsoleLog::ok("Configuration is valid.");
+    }
+    Ok(())
+}
+
+fn report_migration(report: &MigrationReport) {
+    ConsoleLog::ok(format!(
+        "Configuration migrated from version {} to {}: {}",
+        report.from,
+        report.to,
+        report.path.display()
+    ));
+    if let Some(backup) = &report.backup {
+        ConsoleLog::info(format!("Backup saved as {}", backup.display()));
+    }
+}
+
+pub fn run_config_migrate(ctx: &AppContext, explicit: Option<&Path>, dry_run: bool) -> Result<()> {
+    // Vecchio layout: `config.yaml` → `rfortune.conf`
+    if !dry_run && explicit.is_none() {
+        match config::migrate_old_config(ctx) {
+            Ok(Some(backup)) => ConsoleLog::info(format!("Backup saved as {}", backup.display())),
+            Ok(None) => {}
+            Err(e) => ConsoleLog::warn(format!("Migration warning: {e}")),
+        }
+    }
+
+    let path = config::target_config_path(ctx, explicit);
+    let Some(report) = migrate::migrate_file(&path, dry_run)? else {
+        ConsoleLog::ok(format!(
+            "Configuration is already at version {}.",
+            migrate::CURRENT_VERSION
+        ));
+        return Ok(());
+    };
+
+    if dry_run {
+        ConsoleLog::info(format!(
+            "{} would be migrated from version {} to {}:",
+            report.path.display(),
+            report.from,
+            report.to
+        ));
+        for step in &report.steps {
+            println!("  • {step}");
+        }
+        println!();
+        for line in report.diff() {
+            println!("{line}");
+        }
+    } else {
+        report_migration(&report);
     }
+    Ok(())
 }
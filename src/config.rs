@@ -1,134 +1,196 @@
-use crate::log::ConsoleLog;
-use dirs::data_dir;
+use crate::context::AppContext;
+use crate::diagnostics::Diagnostic;
+use crate::encoding::TextEncoding;
+use crate::error::{Error, Result};
+use crate::export::Format;
+use crate::layers::{self, KeyKind};
+use crate::loader::{Delimiter, ParseOptions};
+use crate::migrate;
+use crate::selector::Policy;
+use crate::style::{Character, Style};
+use crate::template::Template;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
-use std::process::Command;
-use std::sync::Mutex;
-use std::sync::OnceLock;
-use std::{env, fs};
-
-#[derive(Debug, Serialize, Deserialize)]
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Config {
+    /// Schema version of the file (see `migrate`); missing in files older than 0.6
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
     pub default_file: Option<String>,
     pub print_title: Option<bool>,
     pub use_cache: Option<bool>,
     #[serde(default)]
     pub fortune_files: Vec<String>,
+    /// Output template, e.g. `"{text}{?author}\n  -- {author}{/author}"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Output style: `plain`, `box`, `ascii` or `bubble`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    /// Character used by the `bubble` style: `cow`, `tux` or `ferris`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character: Option<String>,
+    /// Output format for the quote: `text`, `markdown` or `html`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Selection policy: `weighted`, `uniform`, `shuffle` or `daily`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection: Option<String>,
+    /// Quote delimiter in fortune files: `%` (default), `blank` or any other line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delimiter: Option<String>,
+    /// Ignore `#` comment lines anywhere in fortune files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<bool>,
+    /// Keep quote indentation and blank lines exactly as written in fortune files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preserve_whitespace: Option<bool>,
+    /// Encoding of fortune files: `auto` (default), `utf-8`, `utf-16le`, `utf-16be`, `latin1` or `windows-1252`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Reading speed for `--wait`, in characters per second (default 20)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_chars_per_second: Option<f64>,
+    /// Minimum pause for `--wait`, in seconds (default 6)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_min_seconds: Option<f64>,
+    /// Maximum pause for `--wait`, in seconds (default 60)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_max_seconds: Option<f64>,
+    /// Skip quotes shorter than this many characters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    /// Skip quotes longer than this many characters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    /// Relative weight of each fortune file (files not listed weigh 1, 0 disables a file)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub weights: BTreeMap<String, f64>,
+    /// Profile used when neither `--profile` nor `RFORTUNE_PROFILE` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    /// Named profiles, each overriding any of the keys above
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Mapping>,
 }
 
-static APP_DIR_OVERRIDE: OnceLock<Mutex<HashMap<u64, PathBuf>>> = OnceLock::new();
-
-/// Convert current thread id into a stable u64 (works on stable Rust)
-fn thread_id_u64() -> u64 {
-    let id = std::thread::current().id();
-    let mut hasher = DefaultHasher::new();
-    id.hash(&mut hasher);
-    hasher.finish()
-}
-
-/// Usata nei test per forzare una directory sandbox
-pub fn set_app_dir_for_tests(dir: PathBuf) {
-    let m = APP_DIR_OVERRIDE.get_or_init(|| Mutex::new(HashMap::new()));
-    let mut guard = m.lock().unwrap();
-    let tid = thread_id_u64();
-    guard.insert(tid, dir);
-}
-
-/// Restituisce la directory di configurazione dell'app.
-/// Durante i test, se `set_app_dir_for_tests()` è stato chiamato,
-/// allora viene utilizzata la sandbox invece della directory reale.
-pub fn app_dir() -> PathBuf {
-    if let Some(m) = APP_DIR_OVERRIDE.get() {
-        let guard = m.lock().unwrap();
-        let tid = thread_id_u64();
-        if let Some(dir) = guard.get(&tid).cloned() {
-            return dir;
+/// Sposta configurazione e cache dal vecchio layout (tutto in `legacy_dir`)
+/// alle directory indicate. I file già presenti nella nuova posizione non vengono toccati.
+///
+/// Returns the `(from, to)` pairs that were moved.
+pub fn migrate_layout(
+    legacy_dir: &Path,
+    config_dir: &Path,
+    cache_dir: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut moved = Vec::new();
+
+    let mut candidates: Vec<(PathBuf, PathBuf)> = Vec::new();
+    if legacy_dir != config_dir {
+        for name in ["rfortune.conf", "config.yaml"] {
+            candidates.push((legacy_dir.join(name), config_dir.join(name)));
         }
     }
-
-    // 1️⃣ Caso normale: dirs::data_dir() restituisce un path valido
-    if let Some(mut base) = data_dir() {
-        base.push("rfortune");
-        return base;
+    let legacy_cache = legacy_dir.join("cache");
+    if legacy_cache != cache_dir {
+        candidates.push((legacy_cache, cache_dir.to_path_buf()));
     }
 
-    // 2️⃣ Fallback: se data_dir() == None, prova con $HOME
-    if let Some(home) = dirs::home_dir() {
-        let mut base = home;
-        #[cfg(target_os = "macos")]
-        {
-            base.push("Library");
-            base.push("Application Support");
+    for (from, to) in candidates {
+        if !from.exists() || to.exists() {
+            continue;
         }
-        #[cfg(target_os = "linux")]
-        {
-            base.push(".local");
-            base.push("share");
-        }
-        base.push("rfortune");
-        return base;
+        move_path(&from, &to).map_err(|e| Error::io(&from, e))?;
+        moved.push((from, to));
     }
 
-    // 3️⃣ Ultimo fallback (raro): directory corrente
-    let mut p = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-    p.push(".rfortune");
-    p
+    Ok(moved)
 }
 
-pub fn get_config_path() -> PathBuf {
-    let mut p = app_dir();
-    p.push("rfortune.conf");
-    p
+/// Porta il vecchio layout (tutto nella directory dei dati, es. `~/.local/share/rfortune`)
+/// alle directory del contesto. Non fa nulla quando le directory coincidono
+/// (sistemi diversi da Linux, `RFORTUNE_HOME`, `Paths::in_dir`).
+pub fn migrate_to_xdg_layout(ctx: &AppContext) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let paths = &ctx.paths;
+    migrate_layout(&paths.data_dir, &paths.config_dir, &paths.cache_dir)
 }
 
-/// Percorso di default del file fortune dell'app (rfortune.dat)
-pub fn get_default_path() -> PathBuf {
-    let mut p = app_dir();
-    p.push("rfortune.dat");
-    p
+/// Rinomina un file o una directory; se non è possibile (filesystem diversi) copia ed elimina
+fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_recursive(from, to)?;
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
 }
 
-/// Crea un file di configurazione minimale (se non esiste)
-pub fn init_config_file() -> std::io::Result<()> {
-    let dir = app_dir();
-    fs::create_dir_all(&dir)?;
+fn copy_recursive(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
 
-    if let Err(e) = migrate_old_config() {
-        ConsoleLog::warn(format!("Migration warning: {e}"));
+/// Crea un file di configurazione minimale (se non esiste), insieme al file fortune di default.
+/// Ritorna `false` se il file esisteva già.
+pub fn init_config_file(ctx: &AppContext) -> Result<bool> {
+    for dir in [&ctx.paths.data_dir, &ctx.paths.config_dir] {
+        fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
     }
 
-    let path = get_config_path();
-    if path.exists() {
-        ConsoleLog::info("Configuration file already exists, skipping.");
-        return Ok(());
+    if let Err(e) = migrate_old_config(ctx) {
+        ctx.report(Diagnostic::warn(format!("Migration warning: {e}")));
     }
 
-    if let Err(e) = init_default_file() {
-        ConsoleLog::ko(format!("Error initializing fortune file: {e}"));
+    let path = ctx.paths.config_file();
+    if path.exists() {
+        return Ok(false);
     }
 
+    init_default_file(ctx)?;
+
     let cfg = Config {
-        default_file: Some(get_default_path().to_string_lossy().to_string()),
+        version: Some(migrate::CURRENT_VERSION),
+        default_file: Some(
+            ctx.paths
+                .default_fortune_file()
+                .to_string_lossy()
+                .to_string(),
+        ),
         print_title: Some(true),
         use_cache: Some(true),
-        fortune_files: vec![],
+        ..Default::default()
     };
     let yaml = serde_yaml::to_string(&cfg).expect("Failed to serialize config");
-    fs::write(path, yaml)?;
+    fs::write(&path, yaml).map_err(|e| Error::io(&path, e))?;
 
-    ConsoleLog::ok("Configuration file successfully created.");
-    Ok(())
+    Ok(true)
 }
 
 /// Crea un file fortune di esempio (rfortune.dat) se assente
-pub fn init_default_file() -> std::io::Result<()> {
-    let dir = app_dir();
-    fs::create_dir_all(&dir)?;
+pub fn init_default_file(ctx: &AppContext) -> Result<()> {
+    let dir = &ctx.paths.data_dir;
+    fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
 
-    let path = get_default_path();
+    let path = ctx.paths.default_fortune_file();
     if path.exists() {
         return Ok(()); // non sovrascrivere
     }
@@ -141,135 +203,302 @@ Premature optimization is the root of all evil.
 %
 In Rust we trust.
 "#;
-    fs::write(path, sample)
+    fs::write(&path, sample).map_err(|e| Error::io(&path, e))
 }
 
 /// Carica la configurazione se presente
-pub fn load_config() -> Option<Config> {
-    let path = get_config_path();
-    let content = fs::read_to_string(&path).ok()?;
-    let mut cfg: Config = serde_yaml::from_str(&content).ok()?;
-
-    // ✅ MIGRAZIONE PURA: default_file → fortune_files
-    if cfg.fortune_files.is_empty()
-        && let Some(df) = &cfg.default_file
-    {
-        cfg.fortune_files = vec![df.clone()];
-        let _ = cfg.save();
+pub fn load_config(ctx: &AppContext) -> Option<Config> {
+    load_config_from(ctx, None, None)
+}
+
+/// Carica la configurazione unendo tutti i livelli (sistema, utente, progetto, profilo, ambiente).
+/// Con `explicit` (`--config PATH`) i livelli su file sono sostituiti da quel solo file;
+/// `profile` (`--profile NAME`) ha la precedenza su `RFORTUNE_PROFILE` e `default_profile`.
+pub fn load_config_from(
+    ctx: &AppContext,
+    explicit: Option<&Path>,
+    profile: Option<&str>,
+) -> Option<Config> {
+    let mut layers = layers::config_layers(ctx, explicit);
+    if layers.is_empty() {
+        return None;
     }
 
-    Some(cfg)
+    // Un profilo sconosciuto viene segnalato dalla validazione: qui si usa la configurazione base
+    let _ = layers::apply_profile(&mut layers, profile);
+
+    layers::merge_layers(&layers).ok()
 }
 
-/// Tenta di migrare una vecchia configurazione `config.yaml` a `rfortune.conf`
-pub fn migrate_old_config() -> std::io::Result<()> {
-    let dir = app_dir();
+/// Tenta di migrare una vecchia configurazione `config.yaml` a `rfortune.conf`.
+/// Ritorna la copia di sicurezza del vecchio file, se la migrazione è avvenuta.
+pub fn migrate_old_config(ctx: &AppContext) -> Result<Option<PathBuf>> {
+    let dir = &ctx.paths.config_dir;
     let old_path = dir.join("config.yaml");
-    let new_path = get_config_path();
+    let new_path = ctx.paths.config_file();
 
     // Se non c'è un vecchio file o esiste già il nuovo, non fare nulla
     if !old_path.exists() || new_path.exists() {
-        return Ok(());
+        return Ok(None);
     }
 
-    ConsoleLog::info("Old configuration file detected — attempting migration…");
-
     // Legge e prova a deserializzare il vecchio file
-    match fs::read_to_string(&old_path) {
-        Ok(content) => match serde_yaml::from_str::<Config>(&content) {
-            Ok(cfg) => {
-                // Serializza e salva nel nuovo formato
-                let yaml =
-                    serde_yaml::to_string(&cfg).expect("Failed to serialize migrated config");
-                fs::write(&new_path, yaml)?;
-
-                // Rinomina il vecchio file come backup
-                let backup = dir.join("config.yaml.bak");
-                fs::rename(&old_path, &backup)?;
-
-                ConsoleLog::ok(format!(
-                    "Configuration migrated successfully → {:?}",
-                    new_path
-                ));
-                ConsoleLog::info(format!("Backup saved as {:?}", backup));
-            }
-            Err(e) => {
-                ConsoleLog::ko(format!("Failed to parse old config.yaml: {e}"));
-            }
-        },
-        Err(e) => {
-            ConsoleLog::ko(format!("Failed to read old config.yaml: {e}"));
-        }
-    }
-
-    Ok(())
+    let content = fs::read_to_string(&old_path).map_err(|e| Error::io(&old_path, e))?;
+    let cfg: Config = serde_yaml::from_str(&content).map_err(|e| Error::yaml(&old_path, e))?;
+
+    // Serializza e salva nel nuovo formato
+    let yaml = serde_yaml::to_string(&cfg).expect("Failed to serialize migrated config");
+    fs::write(&new_path, yaml).map_err(|e| Error::io(&new_path, e))?;
+
+    // Rinomina il vecchio file come backup
+    let backup = dir.join("config.yaml.bak");
+    fs::rename(&old_path, &backup).map_err(|e| Error::io(&old_path, e))?;
+
+    ctx.report(Diagnostic::info(format!(
+        "Configuration migrated: {} → {}",
+        old_path.display(),
+        new_path.display()
+    )));
+    Ok(Some(backup))
 }
 
-/// Apre il file di configurazione in modifica.
-/// Se viene specificato `--editor`, usa quello; altrimenti tenta di rilevare l’editor di sistema.
-pub fn run_config_edit(editor_arg: Option<String>) -> std::io::Result<()> {
-    let path: PathBuf = get_config_path();
+impl Config {
+    /// Opzioni di lettura dei file fortune (`delimiter`, `comments`, `preserve_whitespace`, `encoding`)
+    pub fn parse_options(&self) -> Result<ParseOptions> {
+        let delimiter = self
+            .delimiter
+            .as_deref()
+            .map(str::parse::<Delimiter>)
+            .transpose()
+            .map_err(|e| Error::Config(format!("Invalid delimiter in configuration: {e}")))?
+            .unwrap_or_default();
+        let encoding = self
+            .encoding
+            .as_deref()
+            .map(str::parse::<TextEncoding>)
+            .transpose()
+            .map_err(|e| Error::Config(format!("Invalid encoding in configuration: {e}")))?
+            .unwrap_or_default();
+        Ok(ParseOptions {
+            delimiter,
+            comments: self.comments.unwrap_or(false),
+            preserve_whitespace: self.preserve_whitespace.unwrap_or(false),
+            encoding,
+        })
+    }
+
+    /// Salva la configurazione corrente nel file utente del contesto (YAML).
+    pub fn save(&self, ctx: &AppContext) -> Result<()> {
+        self.save_to(&ctx.paths.config_file())
+    }
 
-    // Se non esiste, crealo
-    if !path.exists() {
-        ConsoleLog::warn("Configuration file not found. Creating a new one...");
-        if let Err(e) = init_config_file() {
-            ConsoleLog::ko(format!("Failed to create configuration file: {e}"))
+    /// Salva la configurazione nel file indicato (YAML).
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
         }
+
+        let yaml = serde_yaml::to_string(&self)
+            .map_err(|e| Error::Config(format!("Could not serialize config: {e}")))?;
+
+        fs::write(path, yaml).map_err(|e| Error::io(path, e))
     }
 
-    // 1️⃣ Priorità all'argomento CLI --editor
-    let editor = if let Some(e) = editor_arg {
-        e
-    } else {
-        // 2️⃣ Poi variabili d'ambiente VISUAL o EDITOR
-        if let Ok(visual) = env::var("VISUAL") {
-            visual
-        } else if let Ok(editor) = env::var("EDITOR") {
-            editor
-        } else {
-            // 3️⃣ Fallback per piattaforma
-            if cfg!(target_os = "windows") {
-                "notepad".to_string()
-            } else {
-                "nano".to_string()
+    /// Valore di una singola chiave (`None` se non impostata)
+    pub fn get_value(&self, key: &str) -> Result<Option<Value>> {
+        check_key(key)?;
+        let map = self.to_mapping()?;
+        Ok(map.get(key).filter(|v| !v.is_null()).cloned())
+    }
+
+    /// Imposta una chiave validandone il tipo.
+    ///
+    /// List keys accept several values; with `append` they are added to the current list
+    /// instead of replacing it. Other keys take exactly one value.
+    pub fn set_value(&mut self, key: &str, values: &[String], append: bool) -> Result<()> {
+        let kind = check_key(key)?;
+
+        if kind == KeyKind::Profiles {
+            return Err(Error::Config(format!(
+                "'{key}' cannot be set from the command line; use `rfortune config edit`"
+            )));
+        }
+        let multi = matches!(kind, KeyKind::List | KeyKind::Map);
+        if append && !multi {
+            return Err(Error::Config(format!(
+                "'{key}' is not a list: --append cannot be used"
+            )));
+        }
+        if !multi && values.len() != 1 {
+            return Err(Error::Config(format!("'{key}' takes exactly one value")));
+        }
+
+        let value = match kind {
+            KeyKind::Str => {
+                let v = values[0].clone();
+                validate_str_value(key, &v).map_err(Error::Config)?;
+                Value::String(v)
+            }
+            KeyKind::Bool => layers::parse_bool(&values[0])
+                .map(Value::Bool)
+                .ok_or_else(|| {
+                    Error::Config(format!(
+                        "'{key}' expects a boolean (true/false), got '{}'",
+                        values[0]
+                    ))
+                })?,
+            KeyKind::Float => match values[0].trim().parse::<f64>() {
+                Ok(f) if f.is_finite() && f >= 0.0 => Value::from(f),
+                _ => {
+                    return Err(Error::Config(format!(
+                        "'{key}' expects a non-negative number, got '{}'",
+                        values[0]
+                    )));
+                }
+            },
+            KeyKind::Int => match values[0].trim().parse::<u64>() {
+                Ok(n) => Value::from(n),
+                Err(_) => {
+                    return Err(Error::Config(format!(
+                        "'{key}' expects a non-negative integer, got '{}'",
+                        values[0]
+                    )));
+                }
+            },
+            KeyKind::Map => {
+                let mut map: Mapping = if append {
+                    match self.get_value(key)? {
+                        Some(Value::Mapping(m)) => m,
+                        _ => Mapping::new(),
+                    }
+                } else {
+                    Mapping::new()
+                };
+                for entry in values {
+                    let (file, weight) = parse_weight(entry).ok_or_else(|| {
+                        Error::Config(format!("'{key}' expects FILE=WEIGHT, got '{entry}'"))
+                    })?;
+                    // I pesi interi restano interi nel file (`3`, non `3.0`)
+                    let weight = if weight.fract() == 0.0 && weight <= u32::MAX as f64 {
+                        Value::from(weight as u64)
+                    } else {
+                        Value::from(weight)
+                    };
+                    map.insert(Value::String(file), weight);
+                }
+                Value::Mapping(map)
+            }
+            KeyKind::Profiles => unreachable!("rejected above"),
+            KeyKind::List => {
+                let mut list: Vec<Value> = if append {
+                    match self.get_value(key)? {
+                        Some(Value::Sequence(seq)) => seq,
+                        _ => Vec::new(),
+                    }
+                } else {
+                    Vec::new()
+                };
+                list.extend(values.iter().cloned().map(Value::String));
+                Value::Sequence(list)
             }
+        };
+
+        let mut map = self.to_mapping()?;
+        map.insert(Value::String(key.to_string()), value);
+        *self = serde_yaml::from_value(Value::Mapping(map))
+            .map_err(|e| Error::Config(format!("Invalid value for '{key}': {e}")))?;
+        Ok(())
+    }
+
+    fn to_mapping(&self) -> Result<Mapping> {
+        match serde_yaml::to_value(self) {
+            Ok(Value::Mapping(m)) => Ok(m),
+            Ok(_) => Ok(Mapping::new()),
+            Err(e) => Err(Error::Config(format!("Could not serialize config: {e}"))),
         }
-    };
+    }
+}
 
-    ConsoleLog::info(format!(
-        "Opening configuration file with editor: {}",
-        editor
-    ));
+/// Interpreta una voce `FILE=PESO` di `config set weights`
+fn parse_weight(entry: &str) -> Option<(String, f64)> {
+    let (file, weight) = entry.rsplit_once('=')?;
+    let weight = weight.trim().parse::<f64>().ok()?;
+    (!file.trim().is_empty() && weight.is_finite() && weight >= 0.0)
+        .then(|| (file.trim().to_string(), weight))
+}
 
-    // 4️⃣ Avvia l’editor
-    let status = Command::new(&editor)
-        .arg(path.to_string_lossy().to_string())
-        .status();
+fn check_key(key: &str) -> Result<KeyKind> {
+    layers::key_kind(key).ok_or_else(|| {
+        let names: Vec<&str> = layers::CONFIG_KEYS.iter().map(|(k, _)| *k).collect();
+        Error::Config(format!(
+            "Unknown key '{key}'. Available: {}",
+            names.join(", ")
+        ))
+    })
+}
 
-    match status {
-        Ok(s) if s.success() => ConsoleLog::ok("Configuration file closed successfully."),
-        Ok(_) => ConsoleLog::warn("Editor exited with a non-zero status code."),
-        Err(e) => ConsoleLog::ko(format!("Failed to launch editor '{}': {e}", editor)),
+/// Le chiavi con un insieme chiuso di valori vengono verificate prima del salvataggio
+pub fn validate_str_value(key: &str, value: &str) -> std::result::Result<(), String> {
+    match key {
+        "style" => value.parse::<Style>().map(|_| ()),
+        "character" => value.parse::<Character>().map(|_| ()),
+        "format" => value.parse::<Format>().map(|_| ()),
+        "selection" => value.parse::<Policy>().map(|_| ()),
+        "delimiter" => value.parse::<Delimiter>().map(|_| ()),
+        "encoding" => value.parse::<TextEncoding>().map(|_| ()),
+        "template" => Template::parse(value).map(|_| ()),
+        _ => Ok(()),
     }
+}
 
-    Ok(())
+/// File su cui operano `config set` e `config path`: `--config PATH` o il file utente
+pub fn target_config_path(ctx: &AppContext, explicit: Option<&Path>) -> PathBuf {
+    explicit
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| ctx.paths.config_file())
 }
 
-impl Config {
-    /// Salva la configurazione corrente su disco (YAML).
-    pub fn save(&self) -> Result<(), String> {
-        let path = get_config_path();
-        let parent = path.parent().unwrap();
-
-        if !parent.exists() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Could not create config directory: {e}"))?;
-        }
+/// Legge un singolo file di configurazione, senza unire gli altri livelli.
+/// Un file assente equivale a una configurazione vuota.
+/// I file di versioni precedenti vengono aggiornati in memoria (vedi `migrate`).
+pub fn load_config_file(path: &Path) -> Result<Config> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) if content.trim().is_empty() => return Ok(Config::default()),
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(Error::io(path, e)),
+    };
 
-        let yaml =
-            serde_yaml::to_string(&self).map_err(|e| format!("Could not serialize config: {e}"))?;
+    let mut values: Mapping = serde_yaml::from_str(&content).map_err(|e| Error::yaml(path, e))?;
+    migrate::upgrade(&mut values).map_err(|e| Error::Config(format!("{}: {e}", path.display())))?;
 
-        fs::write(&path, yaml).map_err(|e| format!("Could not write config file: {e}"))
+    serde_yaml::from_value(Value::Mapping(values)).map_err(|e| Error::yaml(path, e))
+}
+
+/// Rappresentazione su una riga di un valore di configurazione
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Sequence(seq) => {
+            let items: Vec<String> = seq.iter().map(format_value).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Mapping(map) => {
+            let items: Vec<String> = map
+                .iter()
+                .map(|(k, v)| format!("{}: {}", format_value(k), format_value(v)))
+                .collect();
+            format!("{{{}}}", items.join(", "))
+        }
+        Value::Null => "~".to_string(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
     }
 }
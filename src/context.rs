@@ -0,0 +1,161 @@
+//! Contesto dell'applicazione: le directory usate da rFortune.
+
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{env, fmt};
+
+/// Variabile d'ambiente che sostituisce le directory dell'app con una sola
+pub const HOME_ENV: &str = "RFORTUNE_HOME";
+
+/// Directory di configurazione, dati e cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    /// Directory of the user configuration file (`rfortune.conf`)
+    pub config_dir: PathBuf,
+    /// Directory of the default fortune file (`rfortune.dat`)
+    pub data_dir: PathBuf,
+    /// Directory of the per-file "last quote" cache
+    pub cache_dir: PathBuf,
+}
+
+impl Paths {
+    /// Tutto in una sola directory, con la cache nella sottodirectory `cache`
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Paths {
+            config_dir: dir.clone(),
+            cache_dir: dir.join("cache"),
+            data_dir: dir,
+        }
+    }
+
+    /// `RFORTUNE_HOME` se impostata, altrimenti le directory della piattaforma
+    pub fn from_env() -> Self {
+        match env::var_os(HOME_ENV).filter(|h| !h.is_empty()) {
+            Some(home) => Paths::in_dir(home),
+            None => Paths::platform(),
+        }
+    }
+
+    /// Directory standard della piattaforma.
+    ///
+    /// On Linux they follow the XDG Base Directory specification
+    /// (`~/.config/rfortune`, `~/.local/share/rfortune`, `~/.cache/rfortune`);
+    /// elsewhere everything lives in the user data directory.
+    pub fn platform() -> Self {
+        let data_dir = platform_data_dir();
+
+        #[cfg(target_os = "linux")]
+        {
+            Paths {
+                config_dir: dirs::config_dir()
+                    .map(|d| d.join("rfortune"))
+                    .unwrap_or_else(|| data_dir.clone()),
+                cache_dir: dirs::cache_dir()
+                    .map(|d| d.join("rfortune"))
+                    .unwrap_or_else(|| data_dir.join("cache")),
+                data_dir,
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Paths::in_dir(data_dir)
+        }
+    }
+
+    /// File di configurazione utente
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join("rfortune.conf")
+    }
+
+    /// File fortune di default (rfortune.dat)
+    pub fn default_fortune_file(&self) -> PathBuf {
+        self.data_dir.join("rfortune.dat")
+    }
+
+    /// File di cache per un determinato file fortune
+    pub fn cache_file(&self, fortune_file: &Path) -> PathBuf {
+        let name = fortune_file
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+            + ".cache";
+        self.cache_dir.join(name)
+    }
+}
+
+/// Directory dei dati dell'utente, con i fallback storici
+fn platform_data_dir() -> PathBuf {
+    // 1️⃣ Caso normale: dirs::data_dir() restituisce un path valido
+    if let Some(base) = dirs::data_dir() {
+        return base.join("rfortune");
+    }
+
+    // 2️⃣ Fallback: se data_dir() == None, prova con $HOME
+    if let Some(home) = dirs::home_dir() {
+        let mut base = home;
+        #[cfg(target_os = "macos")]
+        {
+            base.push("Library");
+            base.push("Application Support");
+        }
+        #[cfg(target_os = "linux")]
+        {
+            base.push(".local");
+            base.push("share");
+        }
+        base.push("rfortune");
+        return base;
+    }
+
+    // 3️⃣ Ultimo fallback (raro): directory corrente
+    env::current_dir()
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join(".rfortune")
+}
+
+/// Contesto passato alle funzioni della libreria
+#[derive(Clone)]
+pub struct AppContext {
+    pub paths: Paths,
+    diagnostics: Option<Arc<dyn Diagnostics>>,
+}
+
+impl AppContext {
+    /// Contesto senza destinazione per i messaggi diagnostici (vengono scartati)
+    pub fn new(paths: Paths) -> Self {
+        AppContext {
+            paths,
+            diagnostics: None,
+        }
+    }
+
+    /// Contesto del processo corrente (`RFORTUNE_HOME` o directory della piattaforma)
+    pub fn from_env() -> Self {
+        AppContext::new(Paths::from_env())
+    }
+
+    /// Invia i messaggi diagnostici della libreria a `sink`
+    pub fn with_diagnostics(mut self, sink: impl Diagnostics + 'static) -> Self {
+        self.diagnostics = Some(Arc::new(sink));
+        self
+    }
+
+    /// Inoltra un messaggio alla destinazione configurata, se presente
+    pub fn report(&self, diagnostic: Diagnostic) {
+        if let Some(sink) = &self.diagnostics {
+            sink.report(&diagnostic);
+        }
+    }
+}
+
+impl fmt::Debug for AppContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppContext")
+            .field("paths", &self.paths)
+            .field("diagnostics", &self.diagnostics.is_some())
+            .finish()
+    }
+}
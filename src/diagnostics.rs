@@ -0,0 +1,51 @@
+//! Messaggi diagnostici della libreria.
+
+use crate::log::LogKind;
+use std::fmt;
+
+/// Un messaggio non bloccante prodotto dalla libreria
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: LogKind,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(kind: LogKind, message: impl Into<String>) -> Self {
+        Diagnostic {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Diagnostic::new(LogKind::Info, message)
+    }
+
+    pub fn warn(message: impl Into<String>) -> Self {
+        Diagnostic::new(LogKind::Warning, message)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Destinazione dei messaggi diagnostici.
+///
+/// Implemented for every `Fn(&Diagnostic)` closure, so a host can simply pass
+/// `|d: &Diagnostic| log::warn!("{d}")`.
+pub trait Diagnostics: Send + Sync {
+    fn report(&self, diagnostic: &Diagnostic);
+}
+
+impl<F> Diagnostics for F
+where
+    F: Fn(&Diagnostic) + Send + Sync,
+{
+    fn report(&self, diagnostic: &Diagnostic) {
+        self(diagnostic)
+    }
+}
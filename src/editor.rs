@@ -0,0 +1,167 @@
+//! Avvio dell'editor esterno per la configurazione e i file fortune.
+
+use crate::context::AppContext;
+use crate::diagnostics::Diagnostic;
+use crate::error::{Error, Result};
+use std::path::Path;
+use std::process::Command;
+use std::{env, fs};
+
+/// Editor da usare: `--editor`, poi `$VISUAL`, poi `$EDITOR`, poi il default della piattaforma
+pub fn resolve_editor(editor_arg: Option<String>) -> String {
+    resolve_editor_with(editor_arg, |name| env::var(name).ok())
+}
+
+/// Come `resolve_editor`, leggendo le variabili d'ambiente con `var`
+pub fn resolve_editor_with(
+    editor_arg: Option<String>,
+    var: impl Fn(&str) -> Option<String>,
+) -> String {
+    // Un valore vuoto (es. `VISUAL=`) passa al candidato successivo
+    let usable = |e: &String| !e.trim().is_empty();
+    editor_arg
+        .filter(usable)
+        .or_else(|| var("VISUAL").filter(usable))
+        .or_else(|| var("EDITOR").filter(usable))
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "windows") {
+                "notepad".to_string()
+            } else {
+                "nano".to_string()
+            }
+        })
+}
+
+/// Divide la stringa dell'editor in programma e argomenti, con le regole della shell.
+///
+/// A string naming an existing file is taken as a whole, so Windows paths with
+/// spaces or backslashes (`C:\Program Files\...\notepad++.exe`) keep working.
+pub fn parse_editor(editor: &str) -> Result<(String, Vec<String>)> {
+    let editor = editor.trim();
+    if Path::new(editor).is_file() {
+        return Ok((editor.to_string(), Vec::new()));
+    }
+
+    let mut words = shell_words::split(editor)
+        .map_err(|e| Error::Editor(format!("Invalid editor command '{editor}': {e}")))?
+        .into_iter();
+    let program = words
+        .next()
+        .ok_or_else(|| Error::Editor("The editor command is empty".to_string()))?;
+
+    Ok((program, words.collect()))
+}
+
+/// Opzione che fa attendere la chiusura del file agli editor grafici più comuni
+fn wait_flag(program: &str) -> Option<&'static str> {
+    let name = Path::new(program).file_stem()?.to_str()?.to_lowercase();
+    match name.as_str() {
+        "code" | "code-insiders" | "codium" | "subl" | "zed" => Some("--wait"),
+        "mate" => Some("-w"),
+        "gvim" | "mvim" => Some("-f"),
+        _ => None,
+    }
+}
+
+/// Comando completo per aprire `path` con l'editor indicato
+pub fn editor_command(editor: &str, path: &Path) -> Result<Command> {
+    let (program, mut args) = parse_editor(editor)?;
+
+    // Senza questa opzione l'editor ritorna subito e la verifica avverrebbe sul file non ancora modificato
+    if let Some(flag) = wait_flag(&program)
+        && !args.iter().any(|a| a == flag)
+    {
+        args.push(flag.to_string());
+    }
+
+    let mut cmd = Command::new(program);
+    cmd.args(args).arg(path);
+    Ok(cmd)
+}
+
+/// Esito di una sessione di modifica
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditOutcome {
+    /// The file was saved and passed the checks
+    Valid,
+    /// The file has errors, but the user chose to keep it
+    KeptInvalid,
+    /// The file had errors and the previous version was restored
+    Restored,
+}
+
+/// Cosa fare dopo una modifica non valida
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AfterError {
+    /// Open the editor again
+    Edit,
+    /// Keep the file with its errors
+    Keep,
+    /// Restore the previous version (or remove a new file)
+    Restore,
+}
+
+/// Apre il file nell'editor e ne verifica i byte alla chiusura con `check`.
+///
+/// When `check` reports errors, `on_error` receives them and decides what to
+/// do; a non-interactive caller can simply return `AfterError::Restore`.
+pub fn edit_validated<F, E>(
+    ctx: &AppContext,
+    editor: &str,
+    path: &Path,
+    check: F,
+    mut on_error: E,
+) -> Result<EditOutcome>
+where
+    F: Fn(&[u8]) -> std::result::Result<(), Vec<String>>,
+    E: FnMut(&[String]) -> Result<AfterError>,
+{
+    let original = fs::read(path).ok();
+
+    loop {
+        ctx.report(Diagnostic::info(format!(
+            "Opening {} with editor: {editor}",
+            path.display()
+        )));
+
+        let status = editor_command(editor, path)?
+            .status()
+            .map_err(|e| Error::Editor(format!("Failed to launch editor '{editor}': {e}")))?;
+        if !status.success() {
+            ctx.report(Diagnostic::warn(
+                "Editor exited with a non-zero status code.",
+            ));
+        }
+
+        // Byte grezzi: i file fortune possono avere un'altra codifica
+        let content = fs::read(path).map_err(|e| Error::io(path, e))?;
+        let errors = match check(&content) {
+            Ok(()) => return Ok(EditOutcome::Valid),
+            Err(errors) => errors,
+        };
+
+        match on_error(&errors)? {
+            AfterError::Edit => continue,
+            AfterError::Keep => {
+                ctx.report(Diagnostic::warn(format!(
+                    "Keeping {} with errors.",
+                    path.display()
+                )));
+                return Ok(EditOutcome::KeptInvalid);
+            }
+            AfterError::Restore => {
+                let result = match &original {
+                    Some(bytes) => fs::write(path, bytes),
+                    None if path.exists() => fs::remove_file(path),
+                    None => Ok(()),
+                };
+                result.map_err(|e| Error::io(path, e))?;
+                ctx.report(Diagnostic::warn(format!(
+                    "Changes discarded, previous version of {} restored.",
+                    path.display()
+                )));
+                return Ok(EditOutcome::Restored);
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Codifica dei file fortune.
+
+use crate::error::{Error, Result};
+use encoding_rs::{UTF_16BE, UTF_16LE, WINDOWS_1252};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// Bytes examined to recognise UTF-16 without a byte order mark
+const UTF16_PROBE_LEN: usize = 4096;
+
+/// Codifica del testo di un file fortune
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// Detect the encoding (BOM, UTF-16, UTF-8, then Windows-1252)
+    #[default]
+    Auto,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1: every byte is the Unicode code point with the same value
+    Latin1,
+    /// Windows-1252 (CP1252): Latin-1 with printable characters in 0x80-0x9F
+    Windows1252,
+}
+
+impl TextEncoding {
+    pub const NAMES: &'static [&'static str] = &[
+        "auto",
+        "utf-8",
+        "utf-16le",
+        "utf-16be",
+        "latin1",
+        "windows-1252",
+    ];
+
+    /// Codifica probabile dei byte indicati (mai `Auto`)
+    pub fn detect(bytes: &[u8]) -> TextEncoding {
+        unicode_signature(bytes).unwrap_or_else(|| {
+            if std::str::from_utf8(bytes).is_ok() {
+                TextEncoding::Utf8
+            } else {
+                TextEncoding::Windows1252
+            }
+        })
+    }
+
+    /// Decodifica i byte senza BOM; ritorna il testo e la codifica usata
+    pub fn decode<'a>(self, bytes: &'a [u8], name: &Path) -> Result<(Cow<'a, str>, TextEncoding)> {
+        let encoding = match self {
+            // Il testo viene validato una volta sola: i file grandi sono quasi sempre UTF-8
+            TextEncoding::Auto => match unicode_signature(bytes) {
+                Some(encoding) => encoding,
+                None => match std::str::from_utf8(bytes) {
+                    Ok(text) => return Ok((Cow::Borrowed(text), TextEncoding::Utf8)),
+                    Err(_) => TextEncoding::Windows1252,
+                },
+            },
+            forced => forced,
+        };
+
+        let text = match encoding {
+            TextEncoding::Auto => unreachable!("Auto is resolved above"),
+            TextEncoding::Utf8 => {
+                let body = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+                match utf8(body, name) {
+                    Ok(text) => Cow::Borrowed(text),
+                    // Un BOM UTF-8 seguito da byte non validi: file salvato male, si usa il ripiego
+                    Err(_) if self == TextEncoding::Auto => {
+                        return TextEncoding::Windows1252.decode(body, name);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                let (codec, bom) = if encoding == TextEncoding::Utf16Le {
+                    (UTF_16LE, UTF16LE_BOM)
+                } else {
+                    (UTF_16BE, UTF16BE_BOM)
+                };
+                let body = bytes.strip_prefix(bom).unwrap_or(bytes);
+                if self == TextEncoding::Auto {
+                    codec.decode_without_bom_handling(body).0
+                } else {
+                    codec
+                        .decode_without_bom_handling_and_without_replacement(body)
+                        .ok_or_else(|| Error::parse(name, None, format!("invalid {encoding}")))?
+                }
+            }
+            TextEncoding::Latin1 => match std::str::from_utf8(bytes) {
+                Ok(ascii) if bytes.is_ascii() => Cow::Borrowed(ascii),
+                _ => Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect()),
+            },
+            TextEncoding::Windows1252 => WINDOWS_1252.decode_without_bom_handling(bytes).0,
+        };
+        Ok((text, encoding))
+    }
+}
+
+/// Codifica indicata dal BOM, o UTF-16 riconosciuto dai byte nulli
+fn unicode_signature(bytes: &[u8]) -> Option<TextEncoding> {
+    if bytes.starts_with(UTF8_BOM) {
+        Some(TextEncoding::Utf8)
+    } else if bytes.starts_with(UTF16LE_BOM) {
+        Some(TextEncoding::Utf16Le)
+    } else if bytes.starts_with(UTF16BE_BOM) {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        detect_utf16(bytes)
+    }
+}
+
+/// UTF-16 senza BOM: testo quasi tutto ASCII, con un byte nullo ogni due
+fn detect_utf16(bytes: &[u8]) -> Option<TextEncoding> {
+    let probe = &bytes[..bytes.len().min(UTF16_PROBE_LEN) & !1];
+    if probe.len() < 4 {
+        return None;
+    }
+    let pairs = probe.len() / 2;
+    let even_nuls = probe.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_nuls = probe.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+    // Nessun testo UTF-8 o a 8 bit contiene byte nulli in quantità
+    if odd_nuls * 10 >= pairs * 7 && even_nuls == 0 {
+        Some(TextEncoding::Utf16Le)
+    } else if even_nuls * 10 >= pairs * 7 && odd_nuls == 0 {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Interpreta i byte come UTF-8, indicando la riga del primo byte non valido
+pub(crate) fn utf8<'a>(bytes: &'a [u8], name: &Path) -> Result<&'a str> {
+    std::str::from_utf8(bytes).map_err(|e| {
+        let line = bytes[..e.valid_up_to()]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count();
+        Error::parse(name, Some(line + 1), "invalid UTF-8")
+    })
+}
+
+impl FromStr for TextEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "auto" => Ok(TextEncoding::Auto),
+            "utf-8" | "utf8" => Ok(TextEncoding::Utf8),
+            "utf-16le" | "utf16le" => Ok(TextEncoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(TextEncoding::Utf16Be),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(TextEncoding::Latin1),
+            "windows-1252" | "cp1252" => Ok(TextEncoding::Windows1252),
+            other => Err(format!(
+                "Unknown encoding '{other}'. Available: {}",
+                TextEncoding::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TextEncoding::Auto => "auto",
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Utf16Le => "utf-16le",
+            TextEncoding::Utf16Be => "utf-16be",
+            TextEncoding::Latin1 => "latin1",
+            TextEncoding::Windows1252 => "windows-1252",
+        };
+        f.write_str(name)
+    }
+}
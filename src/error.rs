@@ -0,0 +1,114 @@
+//! Errori della libreria.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Risultato delle funzioni della libreria
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errore della libreria
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// A file or directory could not be read, written or created
+    #[error("I/O error on '{}': {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A fortune or configuration file is malformed
+    #[error("{}: {message}", Location(path, *line))]
+    Parse {
+        path: PathBuf,
+        /// 1-based line of the problem, when known
+        line: Option<usize>,
+        message: String,
+    },
+
+    /// A fortune file contains no quotes (`path` is `None` when no source provided any)
+    #[error("{}", NoQuotesIn(path.as_deref()))]
+    NoQuotes { path: Option<PathBuf> },
+
+    /// Quotes were found, but none passes the length filters and weights
+    #[error("No quotes match the configured length filters and weights")]
+    NoMatchingQuotes,
+
+    /// Invalid configuration: unknown key or profile, wrong value, unsupported version
+    #[error("{0}")]
+    Config(String),
+
+    /// Invalid output template
+    #[error("Invalid template: {0}")]
+    Template(String),
+
+    /// The cache file could not be locked
+    #[error("Could not lock cache file '{}': {source}", path.display())]
+    CacheLock {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The external editor could not be started
+    #[error("{0}")]
+    Editor(String),
+}
+
+impl Error {
+    /// Errore di I/O relativo al percorso indicato
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Errore di sintassi in un file
+    pub fn parse(
+        path: impl Into<PathBuf>,
+        line: Option<usize>,
+        message: impl Into<String>,
+    ) -> Self {
+        Error::Parse {
+            path: path.into(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Errore di sintassi YAML, con la riga indicata dal parser
+    pub(crate) fn yaml(path: &Path, e: serde_yaml::Error) -> Self {
+        let line = e.location().map(|l| l.line());
+        Error::parse(path, line, e.to_string())
+    }
+}
+
+/// `path:line`, o solo `path` se la riga non è nota
+struct Location<'a>(&'a Path, Option<usize>);
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Some(line) => write!(f, "{}:{line}", self.0.display()),
+            None => write!(f, "{}", self.0.display()),
+        }
+    }
+}
+
+struct NoQuotesIn<'a>(Option<&'a Path>);
+
+impl fmt::Display for NoQuotesIn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(path) => write!(
+                f,
+                "No quotes found in '{}'. The file may be empty or incorrectly formatted",
+                path.display()
+            ),
+            None => write!(f, "No quotes found in any of the fortune files"),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Esportazione di citazioni e raccolte in Markdown e HTML.
+
+use crate::loader::{self, FortuneFile, ParseOptions};
+use crate::template::split_attribution;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Formato di output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Plain text (for `export`: the classic `%`-separated fortune format)
+    #[default]
+    Text,
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub const NAMES: &'static [&'static str] = &["text", "markdown", "html"];
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" | "plain" => Ok(Format::Text),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            other => Err(format!(
+                "Unknown format '{other}'. Available: {}",
+                Format::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Text => "text",
+            Format::Markdown => "markdown",
+            Format::Html => "html",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Rende una singola citazione nel formato richiesto
+pub fn render_quote(quote: &str, format: Format) -> String {
+    match format {
+        Format::Text => quote.to_string(),
+        Format::Markdown => quote_markdown(quote),
+        Format::Html => quote_html(quote),
+    }
+}
+
+/// Rende un intero file fortune: titolo come intestazione e una citazione per blocco.
+///
+/// `fallback_title` is used when the file has no `# Title` line (usually the file name).
+pub fn render_file(file: &FortuneFile, fallback_title: &str, format: Format) -> String {
+    let title = file.title.as_deref().unwrap_or(fallback_title);
+
+    match format {
+        Format::Text => {
+            let mut out = String::new();
+            if let Some(t) = &file.title {
+                out.push_str(&format!("# {t}\n"));
+            }
+            for q in &file.quotes {
+                out.push_str("%\n");
+                // Una riga `%` nel testo va protetta, o diventerebbe un separatore
+                for line in q.lines() {
+                    if line.trim() == "%" {
+                        out.push('\\');
+                    }
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+            out.push_str("%\n");
+            out
+        }
+        Format::Markdown => {
+            let quotes: Vec<String> = file.quotes.iter().map(|q| quote_markdown(q)).collect();
+            format!(
+                "# {}\n\n{}\n",
+                escape_markdown(title),
+                quotes.join("\n\n---\n\n")
+            )
+        }
+        Format::Html => {
+            let quotes: Vec<String> = file.quotes.iter().map(|q| quote_html(q)).collect();
+            format!(
+                "<section class=\"fortune-file\">\n<h1>{}</h1>\n{}\n</section>\n",
+                escape_html(title),
+                quotes.join("\n")
+            )
+        }
+    }
+}
+
+/// Esporta più file fortune in un unico documento
+pub fn export_files(paths: &[&Path], format: Format) -> crate::Result<String> {
+    export_files_with(paths, format, &ParseOptions::default())
+}
+
+/// Come `export_files`, leggendo i file con le opzioni indicate
+pub fn export_files_with(
+    paths: &[&Path],
+    format: Format,
+    options: &ParseOptions,
+) -> crate::Result<String> {
+    loader::check_stdin_once(paths)?;
+    let mut sections = Vec::new();
+
+    for path in paths {
+        let file = FortuneFile::load_with(path, options)?;
+        let fallback = if *path == Path::new(loader::STDIN) {
+            "stdin".to_string()
+        } else {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        };
+        sections.push(render_file(&file, &fallback, format));
+    }
+
+    Ok(match format {
+        Format::Text | Format::Markdown => sections.join("\n"),
+        Format::Html => format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>rFortune</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+            sections.join("")
+        ),
+    })
+}
+
+/// Citazioni con righe rientrate (codice, ASCII art) vanno rese come testo preformattato
+fn is_preformatted(text: &str) -> bool {
+    text.lines().any(|l| l.starts_with([' ', '\t']))
+}
+
+/// Recinto per un blocco di codice: più lungo di qualsiasi sequenza di backtick nel testo
+fn code_fence(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn quote_markdown(quote: &str) -> String {
+    let (text, author) = split_attribution(quote);
+    let mut lines: Vec<String> = Vec::new();
+
+    if is_preformatted(&text) {
+        let fence = code_fence(&text);
+        lines.push(format!("{fence}text"));
+        lines.extend(text.lines().map(str::to_string));
+        lines.push(fence);
+    } else {
+        // Ogni paragrafo mantiene gli a capo originali con un "hard break" (`\`)
+        for paragraph in text.split("\n\n") {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            let rows: Vec<&str> = paragraph.lines().map(str::trim_end).collect();
+            let last = rows.len().saturating_sub(1);
+            for (i, row) in rows.iter().enumerate() {
+                let escaped = escape_markdown(row);
+                lines.push(if i < last {
+                    format!("{escaped}\\")
+                } else {
+                    escaped
+                });
+            }
+        }
+    }
+
+    if let Some(a) = author {
+        lines.push(String::new());
+        lines.push(format!("— *{}*", escape_markdown(&a)));
+    }
+
+    lines
+        .iter()
+        .map(|l| {
+            if l.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {l}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn quote_html(quote: &str) -> String {
+    let (text, author) = split_attribution(quote);
+
+    let body = if is_preformatted(&text) {
+        format!("<pre>{}</pre>", escape_html(&text))
+    } else {
+        text.split("\n\n")
+            .map(|p| {
+                let rows: Vec<String> = p.lines().map(|l| escape_html(l.trim_end())).collect();
+                format!("<p>{}</p>", rows.join("<br>\n"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let caption = author
+        .map(|a| {
+            format!(
+                "\n<figcaption>— <cite>{}</cite></figcaption>",
+                escape_html(&a)
+            )
+        })
+        .unwrap_or_default();
+
+    format!("<figure class=\"fortune\">\n<blockquote>\n{body}\n</blockquote>{caption}\n</figure>")
+}
+
+/// Protegge i caratteri con significato speciale in Markdown
+pub fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    // Un `-` o `+` a inizio riga aprirebbe un elenco
+    if s.starts_with(['-', '+']) {
+        out.push('\\');
+    }
+    // Anche `1.` o `2)` a inizio riga: si protegge il segno dopo il numero
+    let marker = ordered_list_marker(s);
+
+    for (i, c) in s.char_indices() {
+        if Some(i) == marker {
+            out.push('\\');
+        }
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Posizione del `.` o `)` di un elenco numerato a inizio riga (es. `1. testo`)
+fn ordered_list_marker(s: &str) -> Option<usize> {
+    let body = s.trim_start_matches(' ');
+    let digits = body.len() - body.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &body[digits..];
+    let is_marker = (1..=9).contains(&digits)
+        && rest.starts_with(['.', ')'])
+        && rest[1..].chars().next().is_none_or(char::is_whitespace);
+    is_marker.then(|| s.len() - rest.len())
+}
+
+/// Protegge i caratteri con significato speciale in HTML
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,43 @@
+//! Citazione selezionata e risultato della selezione.
+
+use crate::diagnostics::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Identità esatta di una citazione: sorgente e posizione (0-based) al suo interno.
+///
+/// Two identical quotes in different files have different ids, so history,
+/// cache and statistics never mix them up.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct QuoteId {
+    /// Id of the source the quote was selected from (see `SourceMetadata::id`)
+    pub source_id: String,
+    pub index: usize,
+}
+
+/// Una citazione con la sua provenienza
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fortune {
+    /// Text of the quote, without the surrounding `%` delimiters
+    pub text: String,
+    /// Title of the fortune file (`# Title` on its first line)
+    pub title: Option<String>,
+    /// File the quote comes from
+    pub source: PathBuf,
+    /// 1-based position of the quote in its file
+    pub index: usize,
+    /// The quote keeps its layout (`preserve_whitespace`): it is never reflowed
+    pub preformatted: bool,
+    /// Source and position the quote was selected from. For directories this
+    /// is the directory and the position across all its files, while `source`
+    /// and `index` point to the file holding the quote.
+    pub id: QuoteId,
+}
+
+/// Esito di una selezione: la citazione scelta e gli avvisi raccolti
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    pub fortune: Fortune,
+    /// Non-fatal problems, e.g. fortune files that could not be loaded
+    pub warnings: Vec<Diagnostic>,
+}
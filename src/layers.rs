@@ -0,0 +1,437 @@
+//! Configurazione a livelli: sistema, utente, progetto e variabili d'ambiente.
+
+use crate::config::Config;
+use crate::context::AppContext;
+use crate::error::{Error, Result};
+use crate::migrate;
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+/// Nome del file di configurazione di progetto
+pub const PROJECT_CONFIG_NAME: &str = ".rfortune.conf";
+
+/// Prefisso delle variabili d'ambiente che sovrascrivono la configurazione
+pub const ENV_PREFIX: &str = "RFORTUNE_";
+
+/// Variabile d'ambiente che seleziona il profilo attivo
+pub const PROFILE_ENV: &str = "RFORTUNE_PROFILE";
+
+/// Chiavi che non possono comparire dentro un profilo
+pub const NOT_IN_PROFILE: &[&str] = &["profiles", "default_profile"];
+
+/// Tipo di valore di una chiave di configurazione
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    Str,
+    Bool,
+    Float,
+    /// Non-negative integer
+    Int,
+    List,
+    /// Mapping of fortune file → weight
+    Map,
+    /// Mapping of profile name → overridden keys
+    Profiles,
+}
+
+/// Chiavi di configurazione riconosciute, con il relativo tipo
+pub const CONFIG_KEYS: &[(&str, KeyKind)] = &[
+    ("default_file", KeyKind::Str),
+    ("print_title", KeyKind::Bool),
+    ("use_cache", KeyKind::Bool),
+    ("fortune_files", KeyKind::List),
+    ("template", KeyKind::Str),
+    ("style", KeyKind::Str),
+    ("character", KeyKind::Str),
+    ("format", KeyKind::Str),
+    ("selection", KeyKind::Str),
+    ("delimiter", KeyKind::Str),
+    ("comments", KeyKind::Bool),
+    ("preserve_whitespace", KeyKind::Bool),
+    ("encoding", KeyKind::Str),
+    ("wait_chars_per_second", KeyKind::Float),
+    ("wait_min_seconds", KeyKind::Float),
+    ("wait_max_seconds", KeyKind::Float),
+    ("min_length", KeyKind::Int),
+    ("max_length", KeyKind::Int),
+    ("weights", KeyKind::Map),
+    ("default_profile", KeyKind::Str),
+    ("profiles", KeyKind::Profiles),
+];
+
+/// Tipo di una chiave, se riconosciuta
+pub fn key_kind(key: &str) -> Option<KeyKind> {
+    CONFIG_KEYS
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, kind)| *kind)
+}
+
+/// Provenienza di un livello di configurazione
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    System(PathBuf),
+    User(PathBuf),
+    Project(PathBuf),
+    /// File passed with `--config`
+    Explicit(PathBuf),
+    /// Named profile, defined in one of the files
+    Profile(String),
+    Env,
+}
+
+impl ConfigSource {
+    /// Percorso del file, per i livelli basati su file
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ConfigSource::System(p)
+            | ConfigSource::User(p)
+            | ConfigSource::Project(p)
+            | ConfigSource::Explicit(p) => Some(p),
+            ConfigSource::Profile(_) | ConfigSource::Env => None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::System(p) => write!(f, "system ({})", p.display()),
+            ConfigSource::User(p) => write!(f, "user ({})", p.display()),
+            ConfigSource::Project(p) => write!(f, "project ({})", p.display()),
+            ConfigSource::Explicit(p) => write!(f, "--config ({})", p.display()),
+            ConfigSource::Profile(name) => write!(f, "profile '{name}'"),
+            ConfigSource::Env => write!(f, "environment"),
+        }
+    }
+}
+
+/// Un singolo livello: solo le chiavi effettivamente impostate
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    pub source: ConfigSource,
+    pub values: Mapping,
+}
+
+/// Percorso del file di configurazione di sistema
+pub fn system_config_path() -> PathBuf {
+    #[cfg(windows)]
+    {
+        let base = env::var_os("PROGRAMDATA")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"));
+        base.join("rfortune").join("rfortune.conf")
+    }
+    #[cfg(not(windows))]
+    {
+        PathBuf::from("/etc/rfortune/rfortune.conf")
+    }
+}
+
+/// Cerca `.rfortune.conf` risalendo dalla directory indicata
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_NAME))
+        .find(|p| p.is_file())
+}
+
+/// Legge un file di configurazione come mappa YAML (`None` se assente o non valido).
+/// I file di versioni precedenti vengono aggiornati solo in memoria.
+fn read_layer_file(path: &Path) -> Option<Mapping> {
+    let content = fs::read_to_string(path).ok()?;
+    if content.trim().is_empty() {
+        return Some(Mapping::new());
+    }
+    let mut values: Mapping = serde_yaml::from_str(&content).ok()?;
+    // Come per gli errori di sintassi, un file di una versione più recente non viene applicato
+    migrate::upgrade(&mut values).ok()?;
+    Some(values)
+}
+
+/// I percorsi relativi di un file di progetto sono relativi alla sua directory
+fn resolve_relative_paths(values: &mut Mapping, base: &Path) {
+    let resolve = |v: &mut Value| {
+        if let Value::String(s) = v
+            && Path::new(s.as_str()).is_relative()
+        {
+            *s = base.join(s.as_str()).to_string_lossy().to_string();
+        }
+    };
+
+    if let Some(v) = values.get_mut("default_file") {
+        resolve(v);
+    }
+    if let Some(Value::Sequence(list)) = values.get_mut("fortune_files") {
+        list.iter_mut().for_each(resolve);
+    }
+    if let Some(Value::Mapping(weights)) = values.get_mut("weights") {
+        *weights = std::mem::take(weights)
+            .into_iter()
+            .map(|(mut k, v)| {
+                resolve(&mut k);
+                (k, v)
+            })
+            .collect();
+    }
+    if let Some(Value::Mapping(profiles)) = values.get_mut("profiles") {
+        for (_, profile) in profiles.iter_mut() {
+            if let Value::Mapping(p) = profile {
+                resolve_relative_paths(p, base);
+            }
+        }
+    }
+}
+
+/// Converte le variabili `RFORTUNE_*` in un livello di configurazione.
+///
+/// Only known keys are considered; values that do not match the key type are ignored.
+/// `RFORTUNE_FORTUNE_FILES` is a list separated like `PATH` (`:` on Unix, `;` on Windows).
+pub fn env_layer<I>(vars: I) -> Mapping
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut values = Mapping::new();
+
+    for (name, raw) in vars {
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let key = key.to_lowercase();
+        let Some(kind) = key_kind(&key) else {
+            continue;
+        };
+
+        let value = match kind {
+            KeyKind::Str => Some(Value::String(raw)),
+            KeyKind::Bool => parse_bool(&raw).map(Value::Bool),
+            KeyKind::Float => raw.trim().parse::<f64>().ok().map(Value::from),
+            KeyKind::Int => raw.trim().parse::<u64>().ok().map(Value::from),
+            KeyKind::List => Some(Value::Sequence(
+                env::split_paths(&raw)
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .map(|p| Value::String(p.to_string_lossy().to_string()))
+                    .collect(),
+            )),
+            // Le mappe si configurano solo nei file
+            KeyKind::Map | KeyKind::Profiles => None,
+        };
+
+        if let Some(v) = value {
+            values.insert(Value::String(key), v);
+        }
+    }
+
+    values
+}
+
+/// Interpreta un booleano scritto a mano (`true`, `yes`, `1`, `on`, ...)
+pub fn parse_bool(raw: &str) -> Option<bool> {
+    match raw.trim().to_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" | "on" => Some(true),
+        "false" | "no" | "n" | "0" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Raccoglie tutti i livelli presenti, dal meno al più prioritario
+pub fn config_layers(ctx: &AppContext, explicit: Option<&Path>) -> Vec<ConfigLayer> {
+    let mut layers = Vec::new();
+
+    match explicit {
+        Some(path) => {
+            if let Some(values) = read_layer_file(path) {
+                layers.push(ConfigLayer {
+                    source: ConfigSource::Explicit(path.to_path_buf()),
+                    values,
+                });
+            }
+        }
+        None => {
+            let system = system_config_path();
+            if let Some(values) = read_layer_file(&system) {
+                layers.push(ConfigLayer {
+                    source: ConfigSource::System(system),
+                    values,
+                });
+            }
+
+            let user = ctx.paths.config_file();
+            if let Some(values) = read_layer_file(&user) {
+                layers.push(ConfigLayer {
+                    source: ConfigSource::User(user),
+                    values,
+                });
+            }
+
+            if let Some(project) = env::current_dir()
+                .ok()
+                .and_then(|cwd| find_project_config(&cwd))
+                && let Some(mut values) = read_layer_file(&project)
+            {
+                if let Some(dir) = project.parent() {
+                    resolve_relative_paths(&mut values, dir);
+                }
+                layers.push(ConfigLayer {
+                    source: ConfigSource::Project(project),
+                    values,
+                });
+            }
+        }
+    }
+
+    let env_values = env_layer(env::vars());
+    if !env_values.is_empty() {
+        layers.push(ConfigLayer {
+            source: ConfigSource::Env,
+            values: env_values,
+        });
+    }
+
+    layers
+}
+
+/// Unisce i livelli: ogni chiave prende il valore dell'ultimo livello che la imposta.
+/// I profili si uniscono per nome, così un file di progetto può aggiungerne di nuovi.
+pub fn merge_layers(layers: &[ConfigLayer]) -> Result<Config> {
+    let mut merged = Mapping::new();
+    for layer in layers {
+        // Un livello che indica solo `default_file` sostituisce anche l'elenco ereditato
+        if layer.values.contains_key("default_file") && !layer.values.contains_key("fortune_files")
+        {
+            merged.remove("fortune_files");
+        }
+        for (k, v) in &layer.values {
+            if k == "profiles"
+                && let (Some(Value::Mapping(prev)), Value::Mapping(new)) = (merged.get_mut(k), v)
+            {
+                prev.extend(new.clone());
+                continue;
+            }
+            merged.insert(k.clone(), v.clone());
+        }
+    }
+
+    serde_yaml::from_value(Value::Mapping(merged))
+        .map_err(|e| Error::Config(format!("Invalid configuration: {e}")))
+}
+
+/// Valore effettivo di ogni chiave e livello da cui proviene (`None` se non impostata)
+pub fn effective_values(
+    layers: &[ConfigLayer],
+) -> Vec<(&'static str, Option<(Value, ConfigSource)>)> {
+    let mut result: Vec<(&'static str, Option<(Value, ConfigSource)>)> =
+        CONFIG_KEYS.iter().map(|(k, _)| (*k, None)).collect();
+
+    for layer in layers {
+        let resets_list = layer.values.contains_key("default_file")
+            && !layer.values.contains_key("fortune_files");
+
+        for (key, slot) in result.iter_mut() {
+            if let Some(v) = layer.values.get(*key) {
+                if *key == "profiles"
+                    && let (Some((Value::Mapping(prev), source)), Value::Mapping(new)) =
+                        (slot.as_mut(), v)
+                {
+                    prev.extend(new.clone());
+                    *source = layer.source.clone();
+                    continue;
+                }
+                *slot = (!v.is_null()).then(|| (v.clone(), layer.source.clone()));
+            } else if resets_list && *key == "fortune_files" {
+                *slot = None;
+            }
+        }
+    }
+
+    result
+}
+
+/// Nome del profilo attivo: `--profile`, poi `RFORTUNE_PROFILE`, poi `default_profile`
+pub fn active_profile(layers: &[ConfigLayer], requested: Option<&str>) -> Option<String> {
+    if let Some(name) = requested {
+        return Some(name.to_string());
+    }
+    if let Ok(name) = env::var(PROFILE_ENV)
+        && !name.trim().is_empty()
+    {
+        return Some(name.trim().to_string());
+    }
+
+    layers
+        .iter()
+        .rev()
+        .find_map(|l| l.values.get("default_profile"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// Nomi di tutti i profili definiti nei livelli
+pub fn profile_names(layers: &[ConfigLayer]) -> Vec<String> {
+    let mut names: Vec<String> = layers
+        .iter()
+        .filter_map(|l| l.values.get("profiles").and_then(Value::as_mapping))
+        .flat_map(|p| p.keys().filter_map(Value::as_str).map(str::to_string))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Inserisce il livello del profilo attivo subito prima delle variabili d'ambiente.
+///
+/// Returns the name of the applied profile, or an error when the requested
+/// profile is not defined in any layer.
+pub fn apply_profile(
+    layers: &mut Vec<ConfigLayer>,
+    requested: Option<&str>,
+) -> Result<Option<String>> {
+    let Some(name) = active_profile(layers, requested) else {
+        return Ok(None);
+    };
+
+    // Vale la definizione dell'ultimo livello che dichiara il profilo
+    let definition = layers.iter().rev().find_map(|l| {
+        l.values
+            .get("profiles")
+            .and_then(Value::as_mapping)
+            .and_then(|p| p.get(name.as_str()))
+    });
+
+    let mut values = match definition {
+        Some(Value::Mapping(m)) => m.clone(),
+        Some(Value::Null) => Mapping::new(),
+        Some(_) => {
+            return Err(Error::Config(format!(
+                "Profile '{name}' must be a mapping of settings"
+            )));
+        }
+        None => {
+            let names = profile_names(layers);
+            return Err(Error::Config(if names.is_empty() {
+                format!("Unknown profile '{name}': no profiles are defined")
+            } else {
+                format!("Unknown profile '{name}'. Available: {}", names.join(", "))
+            }));
+        }
+    };
+    for key in NOT_IN_PROFILE {
+        values.remove(*key);
+    }
+
+    let pos = layers
+        .iter()
+        .position(|l| l.source == ConfigSource::Env)
+        .unwrap_or(layers.len());
+    layers.insert(
+        pos,
+        ConfigLayer {
+            source: ConfigSource::Profile(name.clone()),
+            values,
+        },
+    );
+
+    Ok(Some(name))
+}
@@ -1,4 +1,21 @@
 pub mod config;
+pub mod context;
+pub mod diagnostics;
+pub mod editor;
+pub mod encoding;
+pub mod error;
+pub mod export;
+pub mod fortune;
+pub mod layers;
 pub mod loader;
 pub mod log;
+pub mod migrate;
+pub mod selector;
+pub mod source;
+pub mod style;
+pub mod template;
 pub mod utils;
+pub mod validate;
+pub mod wrap;
+
+pub use error::{Error, Result};
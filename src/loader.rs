@@ -1,7 +1,12 @@
-use crate::log::ConsoleLog;
+use crate::encoding::TextEncoding;
+use crate::error::{Error, Result};
+use std::borrow::Cow;
+use std::fmt;
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Range;
 use std::path::Path;
+use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct FortuneFile {
@@ -9,72 +14,487 @@ pub struct FortuneFile {
     pub quotes: Vec<String>,
 }
 
+/// Percorso che indica lo standard input (`--file -`)
+pub const STDIN: &str = "-";
+
+/// Errore se lo standard input compare più di una volta tra i percorsi
+pub fn check_stdin_once(paths: &[&Path]) -> Result<()> {
+    let count = paths.iter().filter(|p| **p == Path::new(STDIN)).count();
+    if count > 1 {
+        return Err(Error::Config(format!(
+            "Standard input ('{STDIN}') can be read only once, but it was given {count} times."
+        )));
+    }
+    Ok(())
+}
+
+/// Prefisso delle direttive nell'intestazione di un file, es. `#: delimiter = %%`
+pub const DIRECTIVE_PREFIX: &str = "#:";
+
+/// Separatore tra le citazioni
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delimiter {
+    /// A line containing only this string (`%` in classic fortune files)
+    Line(String),
+    /// One or more blank lines: every paragraph is a quote
+    BlankLine,
+}
+
+impl Default for Delimiter {
+    fn default() -> Self {
+        Delimiter::Line("%".to_string())
+    }
+}
+
+impl FromStr for Delimiter {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err("The delimiter cannot be empty (use 'blank' for blank lines)".to_string()),
+            "blank" | "blank-line" | "paragraph" => Ok(Delimiter::BlankLine),
+            d if d.starts_with('#') || d.starts_with('\\') => Err(format!(
+                "Invalid delimiter '{d}': it cannot start with '#' or '\\'"
+            )),
+            d => Ok(Delimiter::Line(d.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Delimiter::Line(d) => f.write_str(d),
+            Delimiter::BlankLine => f.write_str("blank"),
+        }
+    }
+}
+
+/// Opzioni di lettura dei file fortune (sovrascrivibili dalle direttive `#:` dell'intestazione)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub delimiter: Delimiter,
+    /// Ignore lines starting with `#` anywhere in the file (the title line excluded)
+    pub comments: bool,
+    /// Keep each quote exactly as written (indentation, blank lines, trailing
+    /// spaces), dropping only the newline before the delimiter
+    pub preserve_whitespace: bool,
+    /// Text encoding; in a [`Scan`], the encoding actually used
+    pub encoding: TextEncoding,
+}
+
+/// Errore di sintassi in un file fortune, senza il nome del file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// 1-based line
+    pub line: usize,
+    pub message: String,
+}
+
+impl SyntaxError {
+    /// Errore della libreria per il file indicato
+    pub fn in_file(self, path: &Path) -> Error {
+        Error::parse(path, Some(self.line), self.message)
+    }
+}
+
 impl FortuneFile {
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_file_with(path, &ParseOptions::default())
+    }
+
+    /// Come `from_file`, con opzioni di lettura
+    pub fn from_file_with<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<Self> {
         let path_ref = path.as_ref();
 
-        let file = match fs::File::open(path_ref) {
-            Ok(f) => f,
-            Err(e) => {
-                ConsoleLog::ko(format!("Failed to open file '{}': {e}", path_ref.display()));
-                return Err(format!("Failed to open file: {e}"));
-            }
+        let file = fs::File::open(path_ref).map_err(|e| Error::io(path_ref, e))?;
+        Self::read_with(BufReader::new(file), path_ref, options)
+    }
+
+    /// Come `from_file`, ma `-` legge lo standard input
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load_with(path, &ParseOptions::default())
+    }
+
+    /// Come `load`, con opzioni di lettura
+    pub fn load_with<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<Self> {
+        let path_ref = path.as_ref();
+        if path_ref == Path::new(STDIN) {
+            Self::read_with(io::stdin().lock(), Path::new("<stdin>"), options)
+        } else {
+            Self::from_file_with(path_ref, options)
+        }
+    }
+
+    /// Interpreta un testo già in memoria (es. incluso con `include_str!`)
+    pub fn parse(text: &str) -> Result<Self> {
+        Self::parse_with(text, &ParseOptions::default())
+    }
+
+    /// Come `parse`, con opzioni di lettura
+    pub fn parse_with(text: &str, options: &ParseOptions) -> Result<Self> {
+        Self::read_with(text.as_bytes(), Path::new("<string>"), options)
+    }
+
+    /// Legge un file fortune da qualsiasi sorgente di byte
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        Self::from_buf_reader(BufReader::new(reader))
+    }
+
+    /// Legge un file fortune da un reader bufferizzato
+    pub fn from_buf_reader(reader: impl BufRead) -> Result<Self> {
+        Self::from_named_reader(reader, Path::new("<reader>"))
+    }
+
+    /// Legge un file fortune da un reader; `name` identifica la sorgente nei messaggi d'errore
+    pub fn from_named_reader(reader: impl BufRead, name: &Path) -> Result<Self> {
+        Self::read_with(reader, name, &ParseOptions::default())
+    }
+
+    /// Come `from_named_reader`, con opzioni di lettura
+    pub fn read_with(
+        mut reader: impl BufRead,
+        name: &Path,
+        options: &ParseOptions,
+    ) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|e| Error::io(name, e))?;
+        let (text, scan) = scan_bytes(&bytes, name, options)?;
+        if scan.quotes.is_empty() {
+            return Err(Error::NoQuotes {
+                path: Some(name.to_path_buf()),
+            });
+        }
+
+        Ok(FortuneFile {
+            quotes: (0..scan.quotes.len())
+                .map(|i| scan.quote(&text, i).into_owned())
+                .collect(),
+            title: scan.title,
+        })
+    }
+}
+
+/// Titolo e posizioni (in byte) delle citazioni di un file fortune
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scan {
+    pub title: Option<String>,
+    /// Byte range of each quote, already trimmed; empty quotes are skipped
+    pub quotes: Vec<Range<usize>>,
+    /// Options in effect for the file, after its header directives
+    pub options: ParseOptions,
+    /// The first quote starts in the header, where `\#:` escapes a directive
+    pub header_quote: bool,
+}
+
+impl Scan {
+    /// Testo della citazione `i` di `text` (il testo passato a [`scan`])
+    pub fn quote<'a>(&self, text: &'a str, i: usize) -> Cow<'a, str> {
+        let in_header = i == 0 && self.header_quote;
+        quote_at(text, self.quotes[i].clone(), &self.options, in_header)
+    }
+}
+
+/// Tipo di una riga del file
+enum Line<'a> {
+    Blank,
+    Delimiter,
+    Comment,
+    Directive(&'a str),
+    /// Quote text; `Some` is the position of an escaping backslash to drop (e.g. `\%` → `%`)
+    Text(Option<usize>),
+}
+
+/// Tipo della riga; le direttive valgono solo nell'intestazione (`in_header`)
+fn classify<'a>(line: &'a str, options: &ParseOptions, in_header: bool) -> Line<'a> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return match options.delimiter {
+            Delimiter::BlankLine => Line::Delimiter,
+            Delimiter::Line(_) => Line::Blank,
         };
+    }
+    if let Delimiter::Line(d) = &options.delimiter
+        && trimmed == d
+    {
+        return Line::Delimiter;
+    }
+    if in_header && let Some(directive) = trimmed.strip_prefix(DIRECTIVE_PREFIX) {
+        return Line::Directive(directive);
+    }
+    if options.comments && trimmed.starts_with('#') {
+        return Line::Comment;
+    }
 
-        let reader = BufReader::new(file);
-
-        let mut title: Option<String> = None;
-        let mut quotes: Vec<String> = Vec::new();
-        let mut current_quote = String::new();
-        let mut is_first_line = true;
-
-        for line in reader.lines() {
-            let line = match line {
-                Ok(l) => l,
-                Err(e) => {
-                    ConsoleLog::ko(format!(
-                        "Failed to read line from '{}': {e}",
-                        path_ref.display()
-                    ));
-                    return Err(format!("Failed to read line: {e}"));
-                }
-            };
+    // `\` rende testo letterale il delimitatore, e `#` solo dove sarebbe un commento o una direttiva
+    if let Some(rest) = trimmed.strip_prefix('\\') {
+        let escapes_delimiter = matches!(&options.delimiter, Delimiter::Line(d) if rest == d);
+        let escapes_comment = options.comments && rest.starts_with('#');
+        let escapes_directive = in_header && rest.starts_with(DIRECTIVE_PREFIX);
+        if escapes_delimiter || escapes_comment || escapes_directive {
+            return Line::Text(Some(line.len() - line.trim_start().len()));
+        }
+    }
+    Line::Text(None)
+}
 
-            let trimmed = line.trim();
+/// Applica una direttiva `#: chiave = valore` alle opzioni
+fn apply_directive(directive: &str, options: &mut ParseOptions) -> std::result::Result<(), String> {
+    let (key, value) = directive
+        .split_once('=')
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .ok_or_else(|| {
+            format!(
+                "Invalid directive '{}': expected 'key = value'",
+                directive.trim()
+            )
+        })?;
 
-            if is_first_line && trimmed.starts_with('#') {
-                title = Some(trimmed.trim_start_matches('#').trim().to_string());
+    match key {
+        "delimiter" => options.delimiter = value.parse()?,
+        "comments" => options.comments = switch(key, value)?,
+        "preserve_whitespace" => options.preserve_whitespace = switch(key, value)?,
+        "encoding" => options.encoding = value.parse()?,
+        other => {
+            return Err(format!(
+                "Unknown directive '{other}'. Available: delimiter, comments, preserve_whitespace, encoding"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Valore `on`/`off` di una direttiva
+fn switch(key: &str, value: &str) -> std::result::Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        other => Err(format!(
+            "Invalid value '{other}' for '{key}': use on or off"
+        )),
+    }
+}
+
+/// Individua titolo, direttive e citazioni senza copiarne il testo
+pub fn scan(text: &str, options: &ParseOptions) -> std::result::Result<Scan, SyntaxError> {
+    let mut options = options.clone();
+    let mut title: Option<String> = None;
+    let mut quotes: Vec<Range<usize>> = Vec::new();
+    // Inizio e fine del testo della citazione corrente (senza spazi, salvo `preserve_whitespace`)
+    let mut current: Option<Range<usize>> = None;
+    // La citazione corrente contiene testo, non solo righe vuote o commenti
+    let mut has_text = false;
+    let mut pos = 0;
+    let mut is_first_line = true;
+    let mut in_header = true;
+    let mut header_quote = false;
+
+    for (n, line) in text.split_inclusive('\n').enumerate() {
+        let line_start = pos;
+        pos += line.len();
+
+        let kind = classify(line, &options, in_header);
+        // Con `preserve_whitespace` le righe vuote (anche prima della prima citazione)
+        // e i commenti dentro le citazioni restano nell'intervallo
+        let keeps_line = options.preserve_whitespace
+            && match kind {
+                Line::Blank => true,
+                Line::Comment => !in_header,
+                _ => false,
+            };
+
+        match kind {
+            Line::Directive(directive) => {
+                apply_directive(directive, &mut options).map_err(|message| SyntaxError {
+                    line: n + 1,
+                    message,
+                })?;
+                // Le righe vuote prima di una riga dell'intestazione non sono della citazione
+                current = None;
+            }
+            _ if keeps_line => {
+                // `quote_at` toglie i commenti
+                let range = line_start..line_start + line.len();
+                current = Some(match current {
+                    Some(c) => c.start..range.end,
+                    None => range,
+                });
+            }
+            Line::Blank => {}
+            Line::Text(_) if is_first_line && line.trim_start().starts_with('#') => {
+                title = Some(line.trim().trim_start_matches('#').trim().to_string());
                 is_first_line = false;
-                continue;
+                current = None;
             }
-
-            if is_first_line && !trimmed.is_empty() {
+            Line::Comment if is_first_line => {
+                title = Some(line.trim().trim_start_matches('#').trim().to_string());
                 is_first_line = false;
+                current = None;
             }
-
-            if trimmed == "%" {
-                if !current_quote.trim().is_empty() {
-                    quotes.push(current_quote.trim().to_string());
-                    current_quote.clear();
+            Line::Comment => {
+                is_first_line = false;
+                if in_header {
+                    current = None;
                 }
-            } else {
-                current_quote.push_str(line.as_str());
-                current_quote.push('\n');
+            }
+            Line::Delimiter => {
+                push_quote(text, &mut quotes, current.take(), has_text, &options);
+                has_text = false;
+                is_first_line = false;
+                in_header = false;
+            }
+            Line::Text(_) => {
+                let (begin, end) = if options.preserve_whitespace {
+                    (line_start, line_start + line.len())
+                } else {
+                    let begin = line_start + (line.len() - line.trim_start().len());
+                    (begin, begin + line.trim().len())
+                };
+                header_quote |= in_header;
+                has_text = true;
+                current = Some(match current {
+                    Some(range) => range.start..end,
+                    None => begin..end,
+                });
+                is_first_line = false;
+                in_header = false;
             }
         }
+    }
+    push_quote(text, &mut quotes, current, has_text, &options);
 
-        if !current_quote.trim().is_empty() {
-            quotes.push(current_quote.trim().to_string());
-        }
+    Ok(Scan {
+        title,
+        quotes,
+        options,
+        header_quote,
+    })
+}
 
-        if quotes.is_empty() {
-            ConsoleLog::warn(format!(
-                "No quotes found in '{}'. The file may be empty or incorrectly formatted.",
-                path_ref.display()
-            ));
-            return Err("No quotes found in the file.".to_string());
-        }
+/// Decodifica e analizza un file fortune, rispettando la direttiva `#: encoding`
+pub fn scan_bytes<'a>(
+    bytes: &'a [u8],
+    name: &Path,
+    options: &ParseOptions,
+) -> Result<(Cow<'a, str>, Scan)> {
+    let configured = options.encoding;
+    let (text, used) = match configured.decode(bytes, name) {
+        Ok(decoded) => decoded,
+        Err(e) => match header_encoding(bytes, name, options) {
+            Some(encoding) if encoding != configured => encoding.decode(bytes, name)?,
+            _ => return Err(e),
+        },
+    };
+    let mut found = scan(&text, options).map_err(|e| e.in_file(name))?;
+
+    let wanted = found.options.encoding;
+    if wanted == configured || wanted == used {
+        found.options.encoding = used;
+        return Ok((text, found));
+    }
+
+    let (text, used) = wanted.decode(bytes, name)?;
+    let options = ParseOptions {
+        encoding: wanted,
+        ..options.clone()
+    };
+    let mut found = scan(&text, &options).map_err(|e| e.in_file(name))?;
+    found.options.encoding = used;
+    Ok((text, found))
+}
+
+/// Codifica indicata dall'intestazione del file, letto con la codifica rilevata
+fn header_encoding(bytes: &[u8], name: &Path, options: &ParseOptions) -> Option<TextEncoding> {
+    let (text, _) = TextEncoding::Auto.decode(bytes, name).ok()?;
+    let found = scan(&text, options).ok()?;
+    Some(found.options.encoding)
+}
+
+/// Aggiunge la citazione appena chiusa, senza l'a capo che precede il delimitatore
+fn push_quote(
+    text: &str,
+    quotes: &mut Vec<Range<usize>>,
+    range: Option<Range<usize>>,
+    has_text: bool,
+    options: &ParseOptions,
+) {
+    let Some(mut range) = range.filter(|_| has_text) else {
+        return;
+    };
+    if options.preserve_whitespace {
+        let quote = &text[range.clone()];
+        let kept = quote
+            .strip_suffix('\n')
+            .map(|q| q.strip_suffix('\r').unwrap_or(q))
+            .unwrap_or(quote);
+        range.end = range.start + kept.len();
+    }
+    quotes.push(range);
+}
+
+/// Testo della citazione nell'intervallo indicato, copiato solo se va ripulito
+pub fn quote_at<'a>(
+    text: &'a str,
+    range: Range<usize>,
+    options: &ParseOptions,
+    in_header: bool,
+) -> Cow<'a, str> {
+    let quote = &text[range];
+    let needs_copy = quote.contains('\r')
+        || quote_lines(quote, options, in_header)
+            .any(|(_, kind)| !matches!(kind, Line::Text(None) | Line::Blank));
+    if !needs_copy {
+        return Cow::Borrowed(quote);
+    }
+
+    let lines: Vec<Cow<'_, str>> = quote_lines(quote, options, in_header)
+        .filter_map(|(l, kind)| match kind {
+            Line::Text(Some(backslash)) => Some(Cow::Owned(format!(
+                "{}{}",
+                &l[..backslash],
+                &l[backslash + 1..]
+            ))),
+            Line::Text(None) | Line::Blank => Some(Cow::Borrowed(l)),
+            Line::Comment | Line::Directive(_) | Line::Delimiter => None,
+        })
+        .collect();
+    let quote = lines.join("\n");
+    if options.preserve_whitespace {
+        Cow::Owned(quote)
+    } else {
+        Cow::Owned(quote.trim().to_string())
+    }
+}
+
+/// Righe della citazione con il loro tipo; l'intestazione finisce alla prima riga di testo
+fn quote_lines<'a>(
+    quote: &'a str,
+    options: &'a ParseOptions,
+    mut in_header: bool,
+) -> impl Iterator<Item = (&'a str, Line<'a>)> + 'a {
+    quote.split('\n').map(move |l| {
+        let l = l.strip_suffix('\r').unwrap_or(l);
+        let kind = classify(l, options, in_header);
+        in_header &= !matches!(kind, Line::Text(_));
+        (l, kind)
+    })
+}
+
+/// Converte `\r\n` in `\n`, copiando il testo solo se necessario
+pub fn unix_newlines(text: &str) -> Cow<'_, str> {
+    if text.contains('\r') {
+        Cow::Owned(text.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+impl FromStr for FortuneFile {
+    type Err = Error;
 
-        Ok(FortuneFile { title, quotes })
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
     }
 }
@@ -1,78 +1,322 @@
 use clap::Parser;
 use rfortune::config::Config;
+use rfortune::context::AppContext;
+use rfortune::diagnostics::Diagnostic;
+use rfortune::export::Format;
 use rfortune::log::ConsoleLog;
-use rfortune::utils::ensure_app_initialized;
-use rfortune::{config, utils};
+use rfortune::selector::Policy;
+use rfortune::style::{Character, Style};
+use rfortune::template::Template;
+use rfortune::utils::{InitMode, InitStatus, PrintOptions, WaitOptions, ensure_app_initialized};
+use rfortune::wrap::{self, WrapOptions};
+use rfortune::{Error, Result, config, utils, validate};
+use std::io::{self, ErrorKind, Write};
 use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
 
 mod cli;
 mod commands;
 
 use cli::{CacheAction, Cli, Commands, ConfigAction, FileAction};
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    println!();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            ConsoleLog::ko(&e);
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
 
-    if let Err(e) = ensure_app_initialized() {
-        ConsoleLog::ko(format!("Initialization error: {e}"));
-        return;
+/// Codice di uscita per ogni tipo di errore, secondo `sysexits.h`
+fn exit_code(e: &Error) -> u8 {
+    match e {
+        Error::Parse { .. } => 65,                              // EX_DATAERR
+        Error::NoQuotes { .. } | Error::NoMatchingQuotes => 66, // EX_NOINPUT
+        Error::Io { source, .. } if source.kind() == ErrorKind::NotFound => 66,
+        Error::Editor(_) => 69,                      // EX_UNAVAILABLE
+        Error::Io { .. } => 74,                      // EX_IOERR
+        Error::CacheLock { .. } => 75,               // EX_TEMPFAIL
+        Error::Config(_) | Error::Template(_) => 78, // EX_CONFIG
+        _ => 1,
     }
+}
 
-    // ✅ CARICHIAMO LA CONFIG UNA VOLTA QUI
-    let config = config::load_config().unwrap_or_else(|| {
-        ConsoleLog::warn("No configuration file found. Using defaults.");
-        Config {
-            default_file: Some(config::get_default_path().to_string_lossy().to_string()),
-            print_title: Some(true),
-            use_cache: Some(true),
-            fortune_files: vec![],
+fn run(cli: Cli) -> Result<()> {
+    // I messaggi non bloccanti della libreria finiscono in console
+    let ctx = AppContext::from_env().with_diagnostics(|d: &Diagnostic| ConsoleLog::log(d.kind, d));
+
+    // L'output di `config get` e `config path` è pensato per gli script: niente riga vuota
+    let machine_readable = matches!(
+        &cli.command,
+        Some(Commands::Config {
+            action: ConfigAction::Get { .. } | ConfigAction::Path { .. }
+        })
+    );
+    if !machine_readable {
+        println!();
+    }
+
+    let init_mode = if cli.no_init {
+        InitMode::Never
+    } else if cli.yes {
+        InitMode::Yes
+    } else {
+        InitMode::Ask
+    };
+    if !initialize(&ctx, init_mode)? {
+        ConsoleLog::warn("Initialization aborted by user.");
+        return Ok(());
+    }
+
+    // Validiamo la configurazione prima di usarla, senza ripiegare in silenzio sui default.
+    // I comandi `config` restano utilizzabili anche con una configurazione rotta, per poterla correggere.
+    let is_config_command = matches!(cli.command, Some(Commands::Config { .. }));
+    if !is_config_command {
+        // I suggerimenti (es. `config migrate`) sono mostrati solo da `config validate`
+        let issues: Vec<_> =
+            validate::validate_all(&ctx, cli.config_path.as_deref(), cli.profile.as_deref())
+                .into_iter()
+                .filter(|i| i.severity != validate::Severity::Info)
+                .collect();
+        if !commands::report_issues(&issues) {
+            return Err(Error::Config(
+                "Invalid configuration. Fix the errors above (see `rfortune config validate`)."
+                    .to_string(),
+            ));
         }
-    });
+    }
+
+    // ✅ CARICHIAMO LA CONFIG UNA VOLTA QUI
+    let config = config::load_config_from(&ctx, cli.config_path.as_deref(), cli.profile.as_deref())
+        .unwrap_or_else(|| {
+            if !is_config_command {
+                ConsoleLog::warn("No configuration file found. Using defaults.");
+            }
+            Config {
+                default_file: Some(
+                    ctx.paths
+                        .default_fortune_file()
+                        .to_string_lossy()
+                        .to_string(),
+                ),
+                print_title: Some(true),
+                use_cache: Some(true),
+                ..Default::default()
+            }
+        });
 
     match cli.command {
         // ---------------- CONFIG ----------------
         Some(Commands::Config { action }) => match action {
-            ConfigAction::Init => {
-                commands::run_config_init();
-            }
+            ConfigAction::Init => commands::run_config_init(&ctx),
             ConfigAction::Edit { editor } => {
-                commands::run_config_edit(editor);
+                commands::run_config_edit(&ctx, editor, cli.config_path.as_deref())
+            }
+            ConfigAction::Show => {
+                commands::run_config_show(&ctx, cli.config_path.as_deref(), cli.profile.as_deref())
+            }
+            ConfigAction::Get { key } => commands::run_config_get(
+                &ctx,
+                cli.config_path.as_deref(),
+                cli.profile.as_deref(),
+                &key,
+            ),
+            ConfigAction::Set {
+                key,
+                values,
+                append,
+            } => commands::run_config_set(&ctx, cli.config_path.as_deref(), &key, &values, append),
+            ConfigAction::Validate => commands::run_config_validate(
+                &ctx,
+                cli.config_path.as_deref(),
+                cli.profile.as_deref(),
+            ),
+            ConfigAction::Migrate { dry_run } => {
+                commands::run_config_migrate(&ctx, cli.config_path.as_deref(), dry_run)
+            }
+            ConfigAction::Path { all } => {
+                commands::run_config_path(&ctx, cli.config_path.as_deref(), all)
             }
         },
 
         // ---------------- FILE ----------------
         Some(Commands::File { action }) => match action {
-            FileAction::Init => {
-                commands::run_file_init();
+            FileAction::Init => commands::run_file_init(&ctx),
+            FileAction::Edit { file, editor } => {
+                commands::run_file_edit(&ctx, file, editor, &config)
             }
         },
 
         // ---------------- CACHE ----------------
         Some(Commands::Cache { action }) => match action {
-            CacheAction::Clear => {
-                commands::run_cache_clear();
-            }
+            CacheAction::Clear => commands::run_cache_clear(&ctx),
         },
 
+        // ---------------- EXPORT ----------------
+        Some(Commands::Export {
+            files,
+            format,
+            output,
+        }) => {
+            let sources = utils::resolve_fortune_sources(Some(files), &config);
+            commands::run_export(&sources, format, output, &config.parse_options()?)
+        }
+
         // ---------------- DEFAULT: print random fortune ----------------
         None => {
             // 1. Risolve la PRIORITÀ delle sorgenti
             let sources = utils::resolve_fortune_sources(cli.files.clone(), &config);
 
             if sources.is_empty() {
-                ConsoleLog::ko("No fortune sources configured or provided.");
-                return;
+                return Err(Error::Config(
+                    "No fortune sources configured or provided.".to_string(),
+                ));
             }
 
             // 2. Convertiamo in Path
             let paths: Vec<&Path> = sources.iter().map(Path::new).collect();
 
-            // 3. Stampa citazione casuale da più file
-            if let Err(e) = utils::print_random_from_files(&paths) {
-                ConsoleLog::ko(format!("Failed to print fortune: {e}"));
+            // 3. Opzioni di resa (CLI > config)
+            let template = match cli.template.as_ref().or(config.template.as_ref()) {
+                Some(t) => Some(Template::parse(t).map_err(Error::Template)?),
+                None => None,
+            };
+            let wrap = WrapOptions {
+                width: match cli.width {
+                    Some(0) => None,
+                    Some(w) => Some(w),
+                    None => wrap::terminal_width(),
+                },
+                indent: cli.indent,
+                center: cli.center,
+            };
+            let style = match cli.style {
+                Some(s) => s,
+                None => config
+                    .style
+                    .as_deref()
+                    .map(str::parse::<Style>)
+                    .transpose()
+                    .map_err(|e| Error::Config(format!("Invalid style in configuration: {e}")))?
+                    .unwrap_or_default(),
+            };
+            let character = match cli.character {
+                Some(c) => c,
+                None => config
+                    .character
+                    .as_deref()
+                    .map(str::parse::<Character>)
+                    .transpose()
+                    .map_err(|e| Error::Config(format!("Invalid character in configuration: {e}")))?
+                    .unwrap_or_default(),
+            };
+            let format = match cli.format {
+                Some(f) => f,
+                None => config
+                    .format
+                    .as_deref()
+                    .map(str::parse::<Format>)
+                    .transpose()
+                    .map_err(|e| Error::Config(format!("Invalid format in configuration: {e}")))?
+                    .unwrap_or_default(),
+            };
+            let selection = match cli.selection {
+                Some(p) => p,
+                None => config
+                    .selection
+                    .as_deref()
+                    .map(str::parse::<Policy>)
+                    .transpose()
+                    .map_err(|e| Error::Config(format!("Invalid selection in configuration: {e}")))?
+                    .unwrap_or_default(),
+            };
+            let options = PrintOptions {
+                template,
+                wrap,
+                style,
+                character,
+                print_title: config.print_title.unwrap_or(true),
+                format,
+                show_source: cli.show_source,
+                min_length: config.min_length,
+                max_length: config.max_length,
+                weights: config.weights.clone(),
+                // Con `--no-init` la directory dell'app non va creata nemmeno per la cache
+                use_cache: config.use_cache.unwrap_or(true)
+                    && (!cli.no_init || ctx.paths.data_dir.exists()),
+                selection,
+                parse: config.parse_options()?,
+            };
+
+            // 4. Citazione casuale da più file: la libreria sceglie, qui si stampa
+            let selection = utils::select_fortune(&ctx, &paths, &options)?;
+            for warning in &selection.warnings {
+                ConsoleLog::warn(warning);
+            }
+            println!("{}", utils::render_fortune(&selection.fortune, &options));
+
+            // 5. Pausa di lettura (`-w`), solo su terminale
+            if cli.wait && atty::is(atty::Stream::Stdout) {
+                let wait = wait_options(&config);
+                std::thread::sleep(wait.duration_for(&selection.fortune.text));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Crea la directory dell'applicazione se manca, chiedendo conferma su un terminale.
+/// Ritorna `false` se l'utente rifiuta; senza terminale serve `--yes` o `--no-init`.
+fn initialize(ctx: &AppContext, mode: InitMode) -> Result<bool> {
+    let status = match ensure_app_initialized(ctx, mode)? {
+        InitStatus::NeedsConfirmation => {
+            if !atty::is(atty::Stream::Stdin) {
+                return Err(Error::Config(format!(
+                    "Configuration directory {} not found and no terminal to ask. \
+                     Pass --yes to initialize it or --no-init to run without it.",
+                    ctx.paths.data_dir.display()
+                )));
             }
+            print!("Configuration directory not found. Initialize rFortune now? [Y/n]: ");
+            io::stdout().flush().map_err(|e| Error::io("<stdout>", e))?;
+
+            let mut input = String::new();
+            io::stdin()
+                .read_line(&mut input)
+                .map_err(|e| Error::io("<stdin>", e))?;
+            if matches!(input.trim().to_lowercase().as_str(), "n" | "no") {
+                return Ok(false);
+            }
+            ConsoleLog::info("Initializing rFortune environment...");
+            ensure_app_initialized(ctx, InitMode::Yes)?
         }
+        status => status,
+    };
+
+    if status == InitStatus::Initialized {
+        ConsoleLog::ok("rFortune initialized successfully.");
+    }
+    Ok(true)
+}
+
+/// Parametri di `--wait` dalla configurazione, con i valori classici come default
+fn wait_options(config: &Config) -> WaitOptions {
+    let defaults = WaitOptions::default();
+    let secs = |v: Option<f64>, d: Duration| {
+        v.and_then(|s| Duration::try_from_secs_f64(s).ok())
+            .unwrap_or(d)
+    };
+
+    WaitOptions {
+        chars_per_second: config
+            .wait_chars_per_second
+            .unwrap_or(defaults.chars_per_second),
+        min: secs(config.wait_min_seconds, defaults.min),
+        max: secs(config.wait_max_seconds, defaults.max),
     }
 }
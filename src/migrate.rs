@@ -0,0 +1,212 @@
+//! Versioni dello schema di configurazione e migrazioni.
+
+use crate::error::{Error, Result};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Versione corrente dello schema
+pub const CURRENT_VERSION: u64 = 1;
+
+/// Un passo di migrazione da `from` a `from + 1`
+struct Migration {
+    from: u64,
+    description: &'static str,
+    apply: fn(&mut Mapping),
+}
+
+/// Passi di migrazione, in ordine
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "copy `default_file` into the `fortune_files` list",
+    apply: v0_to_v1,
+}];
+
+/// v0 → v1: `fortune_files` sostituisce `default_file` come elenco delle sorgenti
+fn v0_to_v1(values: &mut Mapping) {
+    let has_list = matches!(values.get("fortune_files"), Some(Value::Sequence(s)) if !s.is_empty());
+    if has_list {
+        return;
+    }
+
+    if let Some(Value::String(df)) = values.get("default_file").cloned() {
+        values.insert(
+            Value::String("fortune_files".into()),
+            Value::Sequence(vec![Value::String(df)]),
+        );
+    }
+}
+
+/// Versione dello schema dichiarata nel file (0 se assente)
+pub fn schema_version(values: &Mapping) -> Result<u64> {
+    match values.get("version") {
+        None | Some(Value::Null) => Ok(0),
+        Some(Value::Number(n)) => n.as_u64().ok_or_else(|| {
+            Error::Config(format!(
+                "'version' must be a non-negative integer, found {n}"
+            ))
+        }),
+        Some(other) => Err(Error::Config(format!(
+            "'version' must be a non-negative integer, found {}",
+            crate::config::format_value(other)
+        ))),
+    }
+}
+
+/// Aggiorna la mappa all'ultima versione; ritorna le descrizioni dei passi applicati.
+///
+/// Files written by a newer rFortune are left untouched and reported as an error.
+pub fn upgrade(values: &mut Mapping) -> Result<Vec<&'static str>> {
+    let mut version = schema_version(values)?;
+    if version > CURRENT_VERSION {
+        return Err(Error::Config(format!(
+            "configuration version {version} is newer than the supported version {CURRENT_VERSION}"
+        )));
+    }
+
+    let mut applied = Vec::new();
+    for step in MIGRATIONS {
+        if step.from < version {
+            continue;
+        }
+        (step.apply)(values);
+        applied.push(step.description);
+        version = step.from + 1;
+    }
+
+    if !applied.is_empty() {
+        // `version` va in cima al file, come in quelli creati da `config init`
+        let mut upgraded = Mapping::new();
+        upgraded.insert(Value::String("version".into()), Value::from(version));
+        upgraded.extend(
+            std::mem::take(values)
+                .into_iter()
+                .filter(|(k, _)| k != "version"),
+        );
+        *values = upgraded;
+    }
+
+    Ok(applied)
+}
+
+/// Risultato (o anteprima) della migrazione di un file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub path: PathBuf,
+    pub from: u64,
+    pub to: u64,
+    /// Descriptions of the applied steps
+    pub steps: Vec<&'static str>,
+    pub old_content: String,
+    pub new_content: String,
+    /// Backup of the original file (`None` for dry runs)
+    pub backup: Option<PathBuf>,
+}
+
+impl MigrationReport {
+    /// Differenza riga per riga (`- `, `+ `, `  ` invariata) fra il vecchio e il nuovo contenuto
+    pub fn diff(&self) -> Vec<String> {
+        let old: Vec<&str> = self.old_content.lines().collect();
+        let new: Vec<&str> = self.new_content.lines().collect();
+
+        // Sottosequenza comune più lunga: i file di configurazione sono piccoli
+        let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                common[i][j] = if old[i] == new[j] {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    common[i + 1][j].max(common[i][j + 1])
+                };
+            }
+        }
+
+        let mut out = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i] == new[j] {
+                out.push(format!("  {}", old[i]));
+                i += 1;
+                j += 1;
+            } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+                out.push(format!("+ {}", new[j]));
+                j += 1;
+            } else {
+                out.push(format!("- {}", old[i]));
+                i += 1;
+            }
+        }
+        out
+    }
+}
+
+/// Percorso della copia di sicurezza creata prima di riscrivere il file
+pub fn backup_path(path: &Path, from: u64) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{from}.bak"));
+    path.with_file_name(name)
+}
+
+/// Primo percorso di backup libero: `backup_path`, poi `<name>.v{from}.1.bak`, `.2.bak`, ...
+fn free_backup_path(path: &Path, from: u64) -> PathBuf {
+    let first = backup_path(path, from);
+    if !first.exists() {
+        return first;
+    }
+    let name = path.file_name().unwrap_or_default().to_os_string();
+    (1..)
+        .map(|n| {
+            let mut candidate = name.clone();
+            candidate.push(format!(".v{from}.{n}.bak"));
+            path.with_file_name(candidate)
+        })
+        .find(|p| !p.exists())
+        .expect("unbounded range")
+}
+
+/// Migra un file di configurazione all'ultima versione.
+///
+/// Returns `Ok(None)` when the file does not exist or is already up to date.
+/// With `dry_run` nothing is written and the report only previews the changes.
+pub fn migrate_file(path: &Path, dry_run: bool) -> Result<Option<MigrationReport>> {
+    let old_content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::io(path, e)),
+    };
+
+    let mut values: Mapping = if old_content.trim().is_empty() {
+        Mapping::new()
+    } else {
+        serde_yaml::from_str(&old_content).map_err(|e| Error::yaml(path, e))?
+    };
+
+    let from = schema_version(&values)?;
+    if from == CURRENT_VERSION {
+        return Ok(None);
+    }
+    let steps = upgrade(&mut values)?;
+
+    let new_content = serde_yaml::to_string(&values)
+        .map_err(|e| Error::Config(format!("Could not serialize config: {e}")))?;
+
+    let mut report = MigrationReport {
+        path: path.to_path_buf(),
+        from,
+        to: CURRENT_VERSION,
+        steps,
+        old_content,
+        new_content,
+        backup: None,
+    };
+
+    if !dry_run {
+        // Un backup di una migrazione precedente non viene mai sovrascritto
+        let backup = free_backup_path(path, from);
+        fs::copy(path, &backup).map_err(|e| Error::io(&backup, e))?;
+        fs::write(path, &report.new_content).map_err(|e| Error::io(path, e))?;
+        report.backup = Some(backup);
+    }
+
+    Ok(Some(report))
+}
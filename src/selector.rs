@@ -0,0 +1,477 @@
+//! Politiche di selezione della citazione.
+
+use crate::context::AppContext;
+use crate::diagnostics::Diagnostic;
+use crate::error::{Error, Result};
+use crate::fortune::QuoteId;
+use crate::loader::ParseOptions;
+use crate::source::{FortuneSource, STDIN_ID};
+use crate::utils::{LastQuote, load_last_cache_with, replace_locked, save_last_cache};
+use rand::Rng;
+use rand::distr::Distribution;
+use rand::distr::weighted::WeightedIndex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Una citazione tra cui scegliere.
+///
+/// Candidates taken from a [`FortuneSource`] read their text only when
+/// [`Candidate::text`] is called, so choosing among many quotes does not copy them.
+#[derive(Clone, Copy)]
+pub struct Candidate<'a> {
+    /// Id of the source holding the quote (see `SourceMetadata::id`)
+    pub source_id: &'a str,
+    /// 0-based position of the quote in its source
+    pub index: usize,
+    text: CandidateText<'a>,
+}
+
+/// Testo di una candidata: già in memoria o da leggere dalla sorgente
+#[derive(Clone, Copy)]
+enum CandidateText<'a> {
+    Loaded(&'a str),
+    Source(&'a dyn FortuneSource),
+}
+
+impl<'a> Candidate<'a> {
+    /// Candidata con il testo già in memoria
+    pub fn new(source_id: &'a str, index: usize, text: &'a str) -> Self {
+        Candidate {
+            source_id,
+            index,
+            text: CandidateText::Loaded(text),
+        }
+    }
+
+    /// Citazione `index` di una sorgente, letta solo quando serve
+    pub fn from_source(source: &'a dyn FortuneSource, index: usize) -> Self {
+        Candidate {
+            source_id: &source.metadata().id,
+            index,
+            text: CandidateText::Source(source),
+        }
+    }
+
+    /// Testo della citazione, preso in prestito quando la sorgente lo consente
+    pub fn text(&self) -> Result<Cow<'a, str>> {
+        match self.text {
+            CandidateText::Loaded(text) => Ok(Cow::Borrowed(text)),
+            CandidateText::Source(source) => Ok(source.quote(self.index)?.unwrap_or_default()),
+        }
+    }
+
+    /// Identità della candidata (sorgente e posizione)
+    pub fn id(&self) -> QuoteId {
+        QuoteId {
+            source_id: self.source_id.to_string(),
+            index: self.index,
+        }
+    }
+}
+
+/// Strategia di scelta tra le citazioni candidate
+pub trait Selector {
+    /// Position in `candidates` of the chosen quote, or `None` if there is nothing to choose
+    fn select(&mut self, candidates: &[Candidate<'_>]) -> Result<Option<usize>>;
+
+    /// Considera solo le candidate accettate da `filter`
+    fn with_filter<F>(self, filter: F) -> Filtered<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&Candidate<'_>) -> bool,
+    {
+        Filtered {
+            inner: self,
+            filter,
+        }
+    }
+}
+
+impl fmt::Debug for Candidate<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Candidate")
+            .field("source_id", &self.source_id)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Selector + ?Sized> Selector for Box<S> {
+    fn select(&mut self, candidates: &[Candidate<'_>]) -> Result<Option<usize>> {
+        (**self).select(candidates)
+    }
+}
+
+/// Politiche disponibili da CLI e configurazione (`--selection`, `selection`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Policy {
+    /// Weighted by source, avoiding the last quote of each file
+    #[default]
+    Weighted,
+    /// Every quote equally likely, avoiding the last quote of each file
+    Uniform,
+    /// Every quote once before any repeats
+    Shuffle,
+    /// The same quote all day
+    Daily,
+}
+
+impl Policy {
+    pub const NAMES: &'static [&'static str] = &["weighted", "uniform", "shuffle", "daily"];
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "weighted" | "random" => Ok(Policy::Weighted),
+            "uniform" => Ok(Policy::Uniform),
+            "shuffle" | "shuffle-bag" => Ok(Policy::Shuffle),
+            "daily" => Ok(Policy::Daily),
+            other => Err(format!(
+                "Unknown selection policy '{other}'. Available: {}",
+                Policy::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Policy::Weighted => "weighted",
+            Policy::Uniform => "uniform",
+            Policy::Shuffle => "shuffle",
+            Policy::Daily => "daily",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Selettore che scarta le candidate rifiutate dal filtro
+pub struct Filtered<S, F> {
+    inner: S,
+    filter: F,
+}
+
+impl<S, F> Selector for Filtered<S, F>
+where
+    S: Selector,
+    F: FnMut(&Candidate<'_>) -> bool,
+{
+    fn select(&mut self, candidates: &[Candidate<'_>]) -> Result<Option<usize>> {
+        let positions: Vec<usize> = (0..candidates.len())
+            .filter(|&i| (self.filter)(&candidates[i]))
+            .collect();
+        // Nessuna esclusa: niente copia delle candidate
+        if positions.len() == candidates.len() {
+            return self.inner.select(candidates);
+        }
+        let kept: Vec<Candidate<'_>> = positions.iter().map(|&i| candidates[i]).collect();
+        Ok(self.inner.select(&kept)?.map(|i| positions[i]))
+    }
+}
+
+/// Scelta casuale uniforme
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Uniform;
+
+impl Selector for Uniform {
+    fn select(&mut self, candidates: &[Candidate<'_>]) -> Result<Option<usize>> {
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(rand::rng().random_range(0..candidates.len())))
+    }
+}
+
+/// Scelta casuale pesata: prima la sorgente secondo il suo peso, poi una sua citazione a caso
+#[derive(Debug, Clone, Default)]
+pub struct Weighted {
+    /// Weight of each source id; sources not listed weigh 1
+    weights: BTreeMap<String, f64>,
+}
+
+impl Weighted {
+    pub fn new(weights: BTreeMap<String, f64>) -> Self {
+        Weighted { weights }
+    }
+
+    /// Peso della sorgente indicata (1 se non specificato)
+    pub fn weight_of(&self, source_id: &str) -> f64 {
+        self.weights.get(source_id).copied().unwrap_or(1.0)
+    }
+}
+
+impl Selector for Weighted {
+    fn select(&mut self, candidates: &[Candidate<'_>]) -> Result<Option<usize>> {
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        let mut sources: Vec<&str> = Vec::new();
+        for c in candidates {
+            if !sources.contains(&c.source_id) {
+                sources.push(c.source_id);
+            }
+        }
+        let dist = WeightedIndex::new(sources.iter().map(|id| self.weight_of(id)))
+            .map_err(|e| Error::Config(format!("Invalid weights: {e}")))?;
+        let mut rng = rand::rng();
+        let source = sources[dist.sample(&mut rng)];
+
+        let positions: Vec<usize> = (0..candidates.len())
+            .filter(|&i| candidates[i].source_id == source)
+            .collect();
+        Ok(Some(positions[rng.random_range(0..positions.len())]))
+    }
+}
+
+/// Dove `NonRepeating` ricorda le citazioni già mostrate
+enum History {
+    /// The last `size` choices, in memory
+    Memory {
+        size: usize,
+        recent: VecDeque<QuoteId>,
+    },
+    /// The last quote of each source, in the cache directory (survives between runs);
+    /// standard input has no stable identity and is never cached
+    Cache(AppContext, ParseOptions),
+}
+
+/// Evita le citazioni mostrate di recente, delegando la scelta a un altro selettore.
+///
+/// If every candidate was shown recently, the history is ignored rather than
+/// failing.
+pub struct NonRepeating<S> {
+    inner: S,
+    history: History,
+}
+
+impl<S: Selector> NonRepeating<S> {
+    /// Ricorda in memoria le ultime `size` scelte (per chi seleziona più volte nello stesso processo)
+    pub fn new(inner: S, size: usize) -> Self {
+        NonRepeating {
+            inner,
+            history: History::Memory {
+                size,
+                recent: VecDeque::new(),
+            },
+        }
+    }
+
+    /// Ricorda l'ultima citazione di ogni sorgente nella cache dell'applicazione.
+    ///
+    /// This is the classic rFortune behaviour (`use_cache`): the same quote is
+    /// never shown twice in a row from the same file. Cache write failures are
+    /// reported through the context diagnostics.
+    pub fn cached(inner: S, ctx: AppContext) -> Self {
+        Self::cached_with(inner, ctx, ParseOptions::default())
+    }
+
+    /// Come `cached`, con le opzioni usate per leggere le sorgenti
+    /// (servono a ritrovare le citazioni nelle cache del vecchio formato)
+    pub fn cached_with(inner: S, ctx: AppContext, options: ParseOptions) -> Self {
+        NonRepeating {
+            inner,
+            history: History::Cache(ctx, options),
+        }
+    }
+
+    fn is_recent(&self, candidate: &Candidate<'_>, last: &[LastQuote]) -> bool {
+        match &self.history {
+            History::Memory { recent, .. } => recent
+                .iter()
+                .any(|id| id.source_id == candidate.source_id && id.index == candidate.index),
+            History::Cache(..) => last.iter().any(|l| l.is(candidate)),
+        }
+    }
+}
+
+impl<S: Selector> Selector for NonRepeating<S> {
+    fn select(&mut self, candidates: &[Candidate<'_>]) -> Result<Option<usize>> {
+        // Ultima citazione di ciascuna sorgente candidata
+        let last: Vec<LastQuote> = match &self.history {
+            History::Cache(ctx, options) => {
+                let mut ids: Vec<&str> = candidates
+                    .iter()
+                    .map(|c| c.source_id)
+                    .filter(|id| *id != STDIN_ID)
+                    .collect();
+                ids.dedup();
+                ids.into_iter()
+                    .filter_map(|id| {
+                        load_last_cache_with(ctx, Path::new(id), options)
+                            .ok()
+                            .flatten()
+                    })
+                    .collect()
+            }
+            History::Memory { .. } => Vec::new(),
+        };
+
+        let positions: Vec<usize> = (0..candidates.len())
+            .filter(|&i| !self.is_recent(&candidates[i], &last))
+            .collect();
+        let choice = if positions.is_empty() || positions.len() == candidates.len() {
+            self.inner.select(candidates)?
+        } else {
+            let fresh: Vec<Candidate<'_>> = positions.iter().map(|&i| candidates[i]).collect();
+            self.inner.select(&fresh)?.map(|i| positions[i])
+        };
+
+        let Some(chosen) = choice.map(|i| candidates[i]) else {
+            return Ok(None);
+        };
+        match &mut self.history {
+            History::Memory { size, recent } => {
+                recent.push_back(chosen.id());
+                while recent.len() > *size {
+                    recent.pop_front();
+                }
+            }
+            History::Cache(..) if chosen.source_id == STDIN_ID => {}
+            History::Cache(ctx, _) => {
+                let saved = chosen.text().and_then(|text| {
+                    save_last_cache(ctx, Path::new(chosen.source_id), chosen.index, &text)
+                });
+                if let Err(e) = saved {
+                    ctx.report(Diagnostic::warn(format!("Could not update cache: {e}")));
+                }
+            }
+        }
+        Ok(choice)
+    }
+}
+
+/// Mostra ogni citazione una volta, in ordine casuale, prima di ricominciare.
+///
+/// Quotes added to the sources join the current round; when every candidate
+/// has been dealt, a new round starts.
+pub struct ShuffleBag {
+    dealt: BTreeSet<QuoteId>,
+    /// File holding the quotes already dealt, when persistent
+    store: Option<PathBuf>,
+    ctx: Option<AppContext>,
+}
+
+impl Default for ShuffleBag {
+    fn default() -> Self {
+        ShuffleBag::new()
+    }
+}
+
+impl ShuffleBag {
+    /// Sacchetto in memoria
+    pub fn new() -> Self {
+        ShuffleBag {
+            dealt: BTreeSet::new(),
+            store: None,
+            ctx: None,
+        }
+    }
+
+    /// Sacchetto salvato nella cache dell'applicazione, condiviso tra esecuzioni
+    pub fn cached(ctx: AppContext) -> Self {
+        let store = ctx.paths.cache_dir.join("shuffle-bag.json");
+        let dealt = fs::read_to_string(&store)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Vec<QuoteId>>(&s).ok())
+            .unwrap_or_default();
+        ShuffleBag {
+            dealt: dealt.into_iter().collect(),
+            store: Some(store),
+            ctx: Some(ctx),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        // Le citazioni dello standard input cambiano a ogni esecuzione
+        let dealt: Vec<&QuoteId> = self
+            .dealt
+            .iter()
+            .filter(|id| id.source_id != STDIN_ID)
+            .collect();
+        let json = serde_json::to_string(&dealt).unwrap_or_default();
+        // Stesso lock della cache dell'ultima citazione; i lettori vedono il file vecchio o quello nuovo
+        replace_locked(store, json.as_bytes())
+    }
+}
+
+impl Selector for ShuffleBag {
+    fn select(&mut self, candidates: &[Candidate<'_>]) -> Result<Option<usize>> {
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+
+        let mut remaining: Vec<usize> = (0..candidates.len())
+            .filter(|&i| !self.dealt.contains(&candidates[i].id()))
+            .collect();
+        if remaining.is_empty() {
+            // Giro completato: si rimette tutto nel sacchetto
+            self.dealt.clear();
+            remaining = (0..candidates.len()).collect();
+        }
+
+        let choice = remaining[rand::rng().random_range(0..remaining.len())];
+        self.dealt.insert(candidates[choice].id());
+        if let Err(e) = self.save()
+            && let Some(ctx) = &self.ctx
+        {
+            ctx.report(Diagnostic::warn(format!(
+                "Could not update shuffle bag: {e}"
+            )));
+        }
+        Ok(Some(choice))
+    }
+}
+
+/// Citazione del giorno: la scelta dipende solo dalla data (UTC) e dalle candidate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Daily {
+    /// Days since 1970-01-01
+    day: u64,
+}
+
+impl Daily {
+    /// Citazione del giorno corrente
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Daily::for_day(secs / 86_400)
+    }
+
+    /// Citazione di un giorno preciso (giorni dal 1970-01-01)
+    pub fn for_day(day: u64) -> Self {
+        Daily { day }
+    }
+}
+
+impl Selector for Daily {
+    fn select(&mut self, candidates: &[Candidate<'_>]) -> Result<Option<usize>> {
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(
+            (splitmix64(self.day) % candidates.len() as u64) as usize,
+        ))
+    }
+}
+
+/// Mescola i bit del giorno: giorni consecutivi danno posizioni non correlate
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,783 @@
+//! Sorgenti di citazioni.
+
+use crate::encoding::{self, TextEncoding};
+use crate::error::{Error, Result};
+use crate::loader::{self, FortuneFile, ParseOptions, Scan};
+use memmap2::Mmap;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fs;
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Id della sorgente letta dallo standard input: non ha una cache persistente
+pub const STDIN_ID: &str = "stdin";
+
+/// Informazioni descrittive di una sorgente
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMetadata {
+    /// Stable identifier, used for weights and for the per-source cache
+    /// (the path, for file-based sources)
+    pub id: String,
+    /// Title of the collection (`# Title` in fortune files)
+    pub title: Option<String>,
+    /// File or directory backing the source, if any
+    pub path: Option<PathBuf>,
+    /// Quotes keep their layout (`preserve_whitespace`) and are never reflowed
+    pub preformatted: bool,
+}
+
+impl SourceMetadata {
+    /// Metadati di una sorgente non legata a un file
+    pub fn new(id: impl Into<String>) -> Self {
+        SourceMetadata {
+            id: id.into(),
+            title: None,
+            path: None,
+            preformatted: false,
+        }
+    }
+
+    /// Metadati di una sorgente letta da `path`
+    pub fn for_path(path: &Path) -> Self {
+        SourceMetadata {
+            id: path.to_string_lossy().to_string(),
+            title: None,
+            path: Some(path.to_path_buf()),
+            preformatted: false,
+        }
+    }
+
+    pub fn with_title(mut self, title: Option<String>) -> Self {
+        self.title = title;
+        self
+    }
+
+    pub fn with_preformatted(mut self, preformatted: bool) -> Self {
+        self.preformatted = preformatted;
+        self
+    }
+}
+
+/// Un fornitore di citazioni indirizzabili per posizione (0-based)
+pub trait FortuneSource {
+    /// Id, title and backing path of the source
+    fn metadata(&self) -> &SourceMetadata;
+
+    /// Number of quotes
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Quote at `index`, or `None` past the end
+    fn get(&self, index: usize) -> Result<Option<String>>;
+
+    /// Come `get`, ma senza copiare il testo quando la sorgente lo consente
+    fn quote(&self, index: usize) -> Result<Option<Cow<'_, str>>> {
+        Ok(self.get(index)?.map(Cow::Owned))
+    }
+
+    /// All quotes, in order
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<String>> + '_> {
+        Box::new((0..self.len()).filter_map(move |i| self.get(i).transpose()))
+    }
+
+    /// Sorgente e posizione effettive della citazione `index` (diverse nelle directory)
+    fn origin(&self, index: usize) -> (&SourceMetadata, usize) {
+        (self.metadata(), index)
+    }
+}
+
+/// Citazioni già in memoria
+#[derive(Debug, Clone)]
+pub struct MemorySource {
+    meta: SourceMetadata,
+    quotes: Vec<String>,
+}
+
+impl MemorySource {
+    pub fn new(id: impl Into<String>, quotes: Vec<String>) -> Self {
+        MemorySource {
+            meta: SourceMetadata::new(id),
+            quotes,
+        }
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.meta.title = Some(title.into());
+        self
+    }
+}
+
+impl FortuneSource for MemorySource {
+    fn metadata(&self) -> &SourceMetadata {
+        &self.meta
+    }
+
+    fn len(&self) -> usize {
+        self.quotes.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Option<String>> {
+        Ok(self.quotes.get(index).cloned())
+    }
+
+    fn quote(&self, index: usize) -> Result<Option<Cow<'_, str>>> {
+        Ok(self.quotes.get(index).map(|q| Cow::Borrowed(q.as_str())))
+    }
+}
+
+/// File fortune classico, con citazioni separate da righe `%`
+#[derive(Debug)]
+pub struct TextSource {
+    meta: SourceMetadata,
+    file: FortuneFile,
+}
+
+impl TextSource {
+    /// Apre un file fortune; `-` legge lo standard input
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with(path, &ParseOptions::default())
+    }
+
+    /// Come `open`, con opzioni di lettura
+    pub fn open_with(path: &Path, options: &ParseOptions) -> Result<Self> {
+        let file = FortuneFile::load_with(path, options)?;
+        let meta = if path == Path::new(loader::STDIN) {
+            SourceMetadata::new(STDIN_ID)
+        } else {
+            SourceMetadata::for_path(path)
+        };
+        Ok(TextSource::new(
+            meta.with_preformatted(options.preserve_whitespace),
+            file,
+        ))
+    }
+
+    /// Sorgente da un file fortune già letto (da stringa o da reader)
+    pub fn new(meta: SourceMetadata, file: FortuneFile) -> Self {
+        let meta = SourceMetadata {
+            title: meta.title.or_else(|| file.title.clone()),
+            ..meta
+        };
+        TextSource { meta, file }
+    }
+}
+
+impl FortuneSource for TextSource {
+    fn metadata(&self) -> &SourceMetadata {
+        &self.meta
+    }
+
+    fn len(&self) -> usize {
+        self.file.quotes.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Option<String>> {
+        Ok(self.file.quotes.get(index).cloned())
+    }
+
+    fn quote(&self, index: usize) -> Result<Option<Cow<'_, str>>> {
+        Ok(self
+            .file
+            .quotes
+            .get(index)
+            .map(|q| Cow::Borrowed(q.as_str())))
+    }
+}
+
+/// File fortune mappato in memoria: si leggono solo le citazioni richieste
+#[derive(Debug)]
+pub struct MappedSource {
+    meta: SourceMetadata,
+    text: MappedText,
+    quotes: Vec<Range<usize>>,
+    options: ParseOptions,
+    /// See `Scan::header_quote`
+    header_quote: bool,
+}
+
+/// Testo di una sorgente mappata
+#[derive(Debug)]
+enum MappedText {
+    /// UTF-8 text read from the map, starting after the byte order mark
+    Mapped { map: Mmap, offset: usize },
+    /// Text converted from another encoding
+    Decoded(String),
+}
+
+impl MappedSource {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with(path, &ParseOptions::default())
+    }
+
+    /// Come `open`, con opzioni di lettura
+    pub fn open_with(path: &Path, options: &ParseOptions) -> Result<Self> {
+        let map = map_file(path)?;
+        let (text, scan) = loader::scan_bytes(&map, path, options)?;
+        let text = match text {
+            // Il testo in prestito è sempre il file senza BOM: basta lo scostamento
+            Cow::Borrowed(text) => {
+                let offset = map.len() - text.len();
+                MappedText::Mapped { map, offset }
+            }
+            Cow::Owned(text) => MappedText::Decoded(text),
+        };
+        let Scan {
+            title,
+            quotes,
+            options,
+            header_quote,
+        } = scan;
+        if quotes.is_empty() {
+            return Err(Error::NoQuotes {
+                path: Some(path.to_path_buf()),
+            });
+        }
+
+        Ok(MappedSource {
+            meta: SourceMetadata::for_path(path)
+                .with_title(title)
+                .with_preformatted(options.preserve_whitespace),
+            text,
+            quotes,
+            options,
+            header_quote,
+        })
+    }
+
+    /// Testo dell'intervallo indicato
+    fn text(&self, range: &Range<usize>) -> Result<&str> {
+        match &self.text {
+            MappedText::Mapped { map, offset } => {
+                // Gli intervalli cadono su confini di carattere di un testo già validato
+                let path = self.meta.path.as_deref().unwrap_or(Path::new(""));
+                encoding::utf8(&map[offset + range.start..offset + range.end], path)
+            }
+            MappedText::Decoded(text) => Ok(&text[range.clone()]),
+        }
+    }
+}
+
+impl FortuneSource for MappedSource {
+    fn metadata(&self) -> &SourceMetadata {
+        &self.meta
+    }
+
+    fn len(&self) -> usize {
+        self.quotes.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Option<String>> {
+        Ok(self.quote(index)?.map(Cow::into_owned))
+    }
+
+    fn quote(&self, index: usize) -> Result<Option<Cow<'_, str>>> {
+        let Some(range) = self.quotes.get(index) else {
+            return Ok(None);
+        };
+        let text = self.text(range)?;
+        let in_header = index == 0 && self.header_quote;
+        Ok(Some(loader::quote_at(
+            text,
+            0..text.len(),
+            &self.options,
+            in_header,
+        )))
+    }
+}
+
+/// Mappa il file in memoria, in sola lettura
+fn map_file(path: &Path) -> Result<Mmap> {
+    let file = fs::File::open(path).map_err(|e| Error::io(path, e))?;
+    // SAFETY: the map is only read, and rFortune never writes a fortune file
+    // while selecting from it. Like every reader of mapped files, a concurrent
+    // truncation by another process is not guarded against.
+    unsafe { Mmap::map(&file) }.map_err(|e| Error::io(path, e))
+}
+
+/// The text is ROT13-encoded (offensive fortunes)
+const STR_ROTATED: u32 = 0x4;
+/// Lines starting with two delimiters are comments
+const STR_COMMENTS: u32 = 0x8;
+const STRFILE_HEADER_LEN: usize = 24;
+
+/// File fortune con l'indice generato da `strfile` (`<file>.dat` accanto al testo)
+#[derive(Debug)]
+pub struct StrfileSource {
+    meta: SourceMetadata,
+    /// The text file, memory-mapped: only the requested quotes are read
+    text: Mmap,
+    offsets: Vec<usize>,
+    delim: u8,
+    flags: u32,
+    /// End of the `# Title` line at the top of the text (0 without a title)
+    title_end: usize,
+}
+
+impl StrfileSource {
+    /// Indice `strfile` del file di testo indicato
+    pub fn index_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".dat");
+        PathBuf::from(name)
+    }
+
+    /// `true` se accanto a `path` c'è un indice `strfile` valido (ne legge solo l'intestazione)
+    pub fn has_index(path: &Path) -> bool {
+        let Ok(mut file) = fs::File::open(Self::index_path(path)) else {
+            return false;
+        };
+        let mut header = [0; STRFILE_HEADER_LEN];
+        let Some((numstr, _, _)) = file
+            .read_exact(&mut header)
+            .ok()
+            .and_then(|_| parse_strfile_header(&header))
+        else {
+            return false;
+        };
+        file.metadata()
+            .is_ok_and(|m| m.len() >= (STRFILE_HEADER_LEN + numstr * 4) as u64)
+    }
+
+    /// Apre il file di testo `path` usando l'indice `<path>.dat`
+    pub fn open(path: &Path) -> Result<Self> {
+        let index_path = Self::index_path(path);
+        let index = fs::read(&index_path).map_err(|e| Error::io(&index_path, e))?;
+        let (flags, delim, offsets) = parse_strfile_index(&index)
+            .ok_or_else(|| Error::parse(&index_path, None, "not a valid strfile index"))?;
+        let text = map_file(path)?;
+
+        if let Some(&bad) = offsets.iter().find(|&&o| o > text.len()) {
+            return Err(Error::parse(
+                &index_path,
+                None,
+                format!("offset {bad} is past the end of '{}'", path.display()),
+            ));
+        }
+
+        let mut source = StrfileSource {
+            meta: SourceMetadata::for_path(path),
+            text,
+            offsets,
+            delim,
+            flags,
+            title_end: 0,
+        };
+        if let Some((title, end)) = strfile_title(&source.text, delim) {
+            source.meta.title = Some(if flags & STR_ROTATED != 0 {
+                rot13(&title)
+            } else {
+                title
+            });
+            source.title_end = end;
+            // Un titolo da solo, prima del primo delimitatore, non è una citazione
+            source
+                .offsets
+                .retain(|&o| o >= end || text_before_delimiter(&source.text[end..], delim));
+        }
+        if source.offsets.is_empty() {
+            return Err(Error::NoQuotes {
+                path: Some(path.to_path_buf()),
+            });
+        }
+        Ok(source)
+    }
+}
+
+/// Numero di citazioni, flag e delimitatore dall'intestazione di un indice `strfile`
+/// (versione 1 o 2, interi big-endian)
+fn parse_strfile_header(data: &[u8]) -> Option<(usize, u32, u8)> {
+    let word = |i: usize| -> Option<u32> {
+        let bytes = data.get(i * 4..i * 4 + 4)?;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?))
+    };
+
+    let version = word(0)?;
+    let numstr = word(1)? as usize;
+    let flags = word(4)?;
+    let delim = *data.get(20)?;
+    if !(1..=2).contains(&version) || !delim.is_ascii_graphic() {
+        return None;
+    }
+    Some((numstr, flags, delim))
+}
+
+/// Intestazione e offset di un indice `strfile`
+fn parse_strfile_index(data: &[u8]) -> Option<(u32, u8, Vec<usize>)> {
+    let (numstr, flags, delim) = parse_strfile_header(data)?;
+    // Segue la tabella di numstr + 1 offset (l'ultimo è la fine del file)
+    if data.len() < STRFILE_HEADER_LEN + numstr * 4 {
+        return None;
+    }
+
+    let offsets = data[STRFILE_HEADER_LEN..]
+        .chunks_exact(4)
+        .take(numstr)
+        .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]) as usize)
+        .collect();
+    Some((flags, delim, offsets))
+}
+
+/// Riga `# Titolo` in cima al testo (dopo eventuali righe vuote) e dove finisce
+fn strfile_title(text: &[u8], delim: u8) -> Option<(String, usize)> {
+    let mut end = 0;
+    for line in text.split_inclusive(|&b| b == b'\n') {
+        end += line.len();
+        let content = line.trim_ascii();
+        if content.is_empty() {
+            continue;
+        }
+        if delim == b'#' || !content.starts_with(b"#") {
+            return None;
+        }
+        let title = String::from_utf8_lossy(content);
+        return Some((title.trim_start_matches('#').trim().to_string(), end));
+    }
+    None
+}
+
+/// `true` se prima del prossimo delimitatore c'è del testo
+fn text_before_delimiter(text: &[u8], delim: u8) -> bool {
+    text.split(|&b| b == b'\n')
+        .map(<[u8]>::trim_ascii)
+        .take_while(|l| *l != [delim])
+        .any(|l| !l.is_empty())
+}
+
+impl FortuneSource for StrfileSource {
+    fn metadata(&self) -> &SourceMetadata {
+        &self.meta
+    }
+
+    fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Option<String>> {
+        Ok(self.quote(index)?.map(Cow::into_owned))
+    }
+
+    fn quote(&self, index: usize) -> Result<Option<Cow<'_, str>>> {
+        let Some(&start) = self.offsets.get(index) else {
+            return Ok(None);
+        };
+        // La prima citazione non comprende il titolo
+        let start = start.max(self.title_end);
+
+        // La citazione va dall'offset alla prossima riga composta dal solo delimitatore
+        let mut end = start;
+        let mut has_comments = false;
+        for line in self.text[start..].split_inclusive(|&b| b == b'\n') {
+            let content = line.strip_suffix(b"\n").unwrap_or(line);
+            let content = content.strip_suffix(b"\r").unwrap_or(content);
+            if content == [self.delim] {
+                break;
+            }
+            has_comments |=
+                self.flags & STR_COMMENTS != 0 && content.starts_with(&[self.delim, self.delim]);
+            end += line.len();
+        }
+        let bytes = &self.text[start..end];
+        let mut quote = match std::str::from_utf8(bytes) {
+            Ok(text) => self.clean(text, has_comments),
+            // Le raccolte storiche sono spesso in Latin-1: si ripiega su Windows-1252
+            Err(_) => {
+                let (text, _) = TextEncoding::Windows1252.decode(bytes, &self.text_path())?;
+                Cow::Owned(self.clean(&text, has_comments).into_owned())
+            }
+        };
+        if self.flags & STR_ROTATED != 0 {
+            quote = Cow::Owned(rot13(&quote));
+        }
+        Ok(Some(quote))
+    }
+}
+
+impl StrfileSource {
+    /// Citazione senza commenti e spazi esterni; il caso comune non copia il testo
+    fn clean<'a>(&self, text: &'a str, has_comments: bool) -> Cow<'a, str> {
+        if has_comments {
+            let comment = String::from_utf8_lossy(&[self.delim, self.delim]).to_string();
+            let kept: Vec<&str> = text
+                .lines()
+                .filter(|l| !l.starts_with(comment.as_str()))
+                .collect();
+            Cow::Owned(kept.join("\n").trim().to_string())
+        } else {
+            loader::unix_newlines(text.trim())
+        }
+    }
+
+    fn text_path(&self) -> PathBuf {
+        self.meta.path.clone().unwrap_or_default()
+    }
+}
+
+fn rot13(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            'a'..='z' => (((c as u8 - b'a') + 13) % 26 + b'a') as char,
+            'A'..='Z' => (((c as u8 - b'A') + 13) % 26 + b'A') as char,
+            _ => c,
+        })
+        .collect()
+}
+
+/// Documento JSON/YAML: una lista di citazioni, con o senza titolo
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Document {
+    List(Vec<String>),
+    Collection {
+        title: Option<String>,
+        quotes: Vec<String>,
+    },
+}
+
+/// Citazioni da un file JSON (`.json`) o YAML (`.yaml`, `.yml`).
+///
+/// Either a plain list of strings or `{ title: ..., quotes: [...] }`.
+#[derive(Debug)]
+pub struct StructuredSource {
+    meta: SourceMetadata,
+    quotes: Vec<String>,
+}
+
+impl StructuredSource {
+    /// Estensioni riconosciute
+    pub const EXTENSIONS: &'static [&'static str] = &["json", "yaml", "yml"];
+
+    /// `true` se l'estensione di `path` indica un formato strutturato
+    pub fn handles(path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| Self::EXTENSIONS.contains(&e.to_lowercase().as_str()))
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+
+        let document: Document = if is_json {
+            serde_json::from_str(&content)
+                .map_err(|e| Error::parse(path, Some(e.line()), e.to_string()))?
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| Error::yaml(path, e))?
+        };
+        let (title, quotes) = match document {
+            Document::List(quotes) => (None, quotes),
+            Document::Collection { title, quotes } => (title, quotes),
+        };
+
+        let quotes: Vec<String> = quotes
+            .iter()
+            .map(|q| q.trim())
+            .filter(|q| !q.is_empty())
+            .map(str::to_string)
+            .collect();
+        if quotes.is_empty() {
+            return Err(Error::NoQuotes {
+                path: Some(path.to_path_buf()),
+            });
+        }
+
+        Ok(StructuredSource {
+            meta: SourceMetadata::for_path(path).with_title(title),
+            quotes,
+        })
+    }
+}
+
+impl FortuneSource for StructuredSource {
+    fn metadata(&self) -> &SourceMetadata {
+        &self.meta
+    }
+
+    fn len(&self) -> usize {
+        self.quotes.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Option<String>> {
+        Ok(self.quotes.get(index).cloned())
+    }
+
+    fn quote(&self, index: usize) -> Result<Option<Cow<'_, str>>> {
+        Ok(self.quotes.get(index).map(|q| Cow::Borrowed(q.as_str())))
+    }
+}
+
+/// Tutti i file fortune di una directory (non ricorsiva), in ordine di nome.
+///
+/// Hidden files and `strfile` indexes are ignored; files that cannot be loaded
+/// are left out and listed by [`DirectorySource::skipped`].
+pub struct DirectorySource {
+    meta: SourceMetadata,
+    sources: Vec<Box<dyn FortuneSource>>,
+    /// Cumulative quote count before each source
+    starts: Vec<usize>,
+    len: usize,
+    skipped: Vec<Error>,
+}
+
+impl DirectorySource {
+    pub fn open(dir: &Path) -> Result<Self> {
+        Self::open_with(dir, &ParseOptions::default())
+    }
+
+    /// Come `open`, con opzioni di lettura per i file fortune di testo
+    pub fn open_with(dir: &Path, options: &ParseOptions) -> Result<Self> {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| Error::io(dir, e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file())
+            .collect();
+        files.sort();
+
+        let mut sources: Vec<Box<dyn FortuneSource>> = Vec::new();
+        let mut skipped = Vec::new();
+        for path in &files {
+            let hidden = path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            let is_index = path.extension().is_some_and(|e| e == "dat")
+                && files.contains(&path.with_extension(""))
+                && StrfileSource::has_index(&path.with_extension(""));
+            if hidden || is_index {
+                continue;
+            }
+            match open_source_with(path, options) {
+                Ok(source) => sources.push(source),
+                Err(e) => skipped.push(e),
+            }
+        }
+
+        let mut starts = Vec::with_capacity(sources.len());
+        let mut len = 0;
+        for source in &sources {
+            starts.push(len);
+            len += source.len();
+        }
+
+        Ok(DirectorySource {
+            meta: SourceMetadata::for_path(dir),
+            sources,
+            starts,
+            len,
+            skipped,
+        })
+    }
+
+    /// Errori dei file della directory che non è stato possibile caricare
+    pub fn skipped(&self) -> &[Error] {
+        &self.skipped
+    }
+
+    /// Consuma gli errori dei file scartati
+    pub fn take_skipped(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.skipped)
+    }
+
+    /// Sorgente che contiene la citazione `index` e posizione al suo interno
+    fn locate(&self, index: usize) -> Option<(&dyn FortuneSource, usize)> {
+        if index >= self.len {
+            return None;
+        }
+        let pos = self.starts.partition_point(|&start| start <= index) - 1;
+        Some((self.sources[pos].as_ref(), index - self.starts[pos]))
+    }
+}
+
+impl FortuneSource for DirectorySource {
+    fn metadata(&self) -> &SourceMetadata {
+        &self.meta
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Result<Option<String>> {
+        match self.locate(index) {
+            Some((source, i)) => source.get(i),
+            None => Ok(None),
+        }
+    }
+
+    fn quote(&self, index: usize) -> Result<Option<Cow<'_, str>>> {
+        match self.locate(index) {
+            Some((source, i)) => source.quote(i),
+            None => Ok(None),
+        }
+    }
+
+    fn origin(&self, index: usize) -> (&SourceMetadata, usize) {
+        match self.locate(index) {
+            Some((source, i)) => source.origin(i),
+            None => (&self.meta, index),
+        }
+    }
+}
+
+/// Apre la sorgente adatta al percorso: standard input (`-`), directory,
+/// JSON/YAML, file con indice `strfile` o file fortune di testo.
+pub fn open_source(path: &Path) -> Result<Box<dyn FortuneSource>> {
+    open_source_with(path, &ParseOptions::default())
+}
+
+/// Come `open_source`; le opzioni valgono per i file fortune di testo
+pub fn open_source_with(path: &Path, options: &ParseOptions) -> Result<Box<dyn FortuneSource>> {
+    Ok(if path == Path::new(loader::STDIN) {
+        Box::new(TextSource::open_with(path, options)?)
+    } else if path.is_dir() {
+        Box::new(DirectorySource::open_with(path, options)?)
+    } else if StructuredSource::handles(path) {
+        Box::new(StructuredSource::open(path)?)
+    } else if StrfileSource::has_index(path) {
+        Box::new(StrfileSource::open(path)?)
+    } else if path.is_file() {
+        Box::new(MappedSource::open_with(path, options)?)
+    } else {
+        // Pipe, dispositivi e percorsi inesistenti: lettura tradizionale
+        Box::new(TextSource::open_with(path, options)?)
+    })
+}
+
+/// Apre più sorgenti; quelle che falliscono (e i file scartati dalle
+/// directory) sono restituiti come errori a parte, nell'ordine dei percorsi.
+pub fn open_sources(paths: &[&Path]) -> (Vec<Box<dyn FortuneSource>>, Vec<Error>) {
+    open_sources_with(paths, &ParseOptions::default())
+}
+
+/// Come `open_sources`, con opzioni di lettura per i file fortune di testo
+pub fn open_sources_with(
+    paths: &[&Path],
+    options: &ParseOptions,
+) -> (Vec<Box<dyn FortuneSource>>, Vec<Error>) {
+    let mut sources: Vec<Box<dyn FortuneSource>> = Vec::new();
+    let mut failures = Vec::new();
+
+    for path in paths {
+        if path.is_dir() {
+            match DirectorySource::open_with(path, options) {
+                Ok(mut dir) => {
+                    failures.extend(dir.take_skipped());
+                    sources.push(Box::new(dir));
+                }
+                Err(e) => failures.push(e),
+            }
+            continue;
+        }
+        match open_source_with(path, options) {
+            Ok(source) => sources.push(source),
+            Err(e) => failures.push(e),
+        }
+    }
+
+    (sources, failures)
+}
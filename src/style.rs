@@ -0,0 +1,261 @@
+//! Cornici per la citazione: box Unicode/ASCII e fumetto in stile cowsay.
+
+use crate::wrap::{WrapOptions, display_width, format_text};
+use std::fmt;
+use std::str::FromStr;
+
+/// Columns taken by the frame on each line (border + padding on both sides)
+const FRAME_OVERHEAD: usize = 4;
+
+/// Stile di resa della citazione
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Style {
+    /// Plain text, no frame
+    #[default]
+    Plain,
+    /// Box drawn with Unicode line characters
+    Box,
+    /// Box drawn with plain ASCII characters
+    Ascii,
+    /// Speech bubble spoken by a character, like `cowsay`
+    Bubble,
+}
+
+impl Style {
+    pub const NAMES: &'static [&'static str] = &["plain", "box", "ascii", "bubble"];
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "plain" | "none" => Ok(Style::Plain),
+            "box" => Ok(Style::Box),
+            "ascii" => Ok(Style::Ascii),
+            "bubble" | "cowsay" => Ok(Style::Bubble),
+            other => Err(format!(
+                "Unknown style '{other}'. Available: {}",
+                Style::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Style::Plain => "plain",
+            Style::Box => "box",
+            Style::Ascii => "ascii",
+            Style::Bubble => "bubble",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Personaggi inclusi per lo stile `bubble`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Character {
+    #[default]
+    Cow,
+    Tux,
+    Ferris,
+}
+
+impl Character {
+    pub const NAMES: &'static [&'static str] = &["cow", "tux", "ferris"];
+
+    fn art(&self) -> &'static str {
+        match self {
+            Character::Cow => {
+                r#"        \   ^__^
+         \  (oo)\_______
+            (__)\       )\/\
+                ||----w |
+                ||     ||"#
+            }
+            Character::Tux => {
+                r#"        \
+         \
+            .--.
+           |o_o |
+           |:_/ |
+          //   \ \
+         (|     | )
+        /'\_   _/`\
+        \___)=(___/"#
+            }
+            Character::Ferris => {
+                r#"        \
+         \
+            _~^~^~_
+        \) /  o o  \ (/
+          '_   -   _'
+          / '-----' \"#
+            }
+        }
+    }
+}
+
+impl FromStr for Character {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "cow" => Ok(Character::Cow),
+            "tux" => Ok(Character::Tux),
+            "ferris" | "crab" => Ok(Character::Ferris),
+            other => Err(format!(
+                "Unknown character '{other}'. Available: {}",
+                Character::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+struct BoxChars {
+    top_left: &'static str,
+    top_right: &'static str,
+    bottom_left: &'static str,
+    bottom_right: &'static str,
+    horizontal: &'static str,
+    vertical: &'static str,
+}
+
+const UNICODE_BOX: BoxChars = BoxChars {
+    top_left: "┌",
+    top_right: "┐",
+    bottom_left: "└",
+    bottom_right: "┘",
+    horizontal: "─",
+    vertical: "│",
+};
+
+const ASCII_BOX: BoxChars = BoxChars {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    horizontal: "-",
+    vertical: "|",
+};
+
+/// Impagina il testo e lo racchiude nella cornice richiesta.
+///
+/// `title`, when present, is shown in the top border of boxes and bubbles.
+pub fn render(
+    text: &str,
+    title: Option<&str>,
+    style: Style,
+    character: Character,
+    layout: &WrapOptions,
+) -> String {
+    if style == Style::Plain {
+        return format_text(text, layout);
+    }
+
+    // Il testo viene impaginato nello spazio interno alla cornice
+    let inner = WrapOptions {
+        width: layout
+            .width
+            .map(|w| w.saturating_sub(layout.indent + FRAME_OVERHEAD).max(1)),
+        indent: 0,
+        center: layout.center,
+    };
+    let body = format_text(&text.replace('\t', "    "), &inner);
+    let lines: Vec<&str> = body.lines().collect();
+    let title = title.map(str::trim).filter(|t| !t.is_empty());
+
+    let framed = match style {
+        Style::Box => draw_box(&lines, title, &UNICODE_BOX),
+        Style::Ascii => draw_box(&lines, title, &ASCII_BOX),
+        Style::Bubble => format!("{}\n{}", draw_bubble(&lines, title), character.art()),
+        Style::Plain => unreachable!(),
+    };
+
+    let pad = " ".repeat(layout.indent);
+    framed
+        .lines()
+        .map(|l| format!("{pad}{l}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Larghezza interna della cornice: la riga più lunga o il titolo
+fn inner_width(lines: &[&str], title: Option<&str>) -> usize {
+    let content = lines.iter().map(|l| display_width(l)).max().unwrap_or(0);
+    let title = title.map(|t| display_width(t) + 2).unwrap_or(0);
+    content.max(title)
+}
+
+fn pad_right(line: &str, width: usize) -> String {
+    let fill = width.saturating_sub(display_width(line));
+    format!("{line}{}", " ".repeat(fill))
+}
+
+/// Bordo superiore con il titolo incorporato, es. `┌─ Title ───┐`
+fn top_border(width: usize, title: Option<&str>, left: &str, right: &str, h: &str) -> String {
+    match title {
+        Some(t) => {
+            let used = display_width(t) + 3;
+            format!(
+                "{left}{h} {t} {}{right}",
+                h.repeat((width + 2).saturating_sub(used))
+            )
+        }
+        None => format!("{left}{}{right}", h.repeat(width + 2)),
+    }
+}
+
+fn draw_box(lines: &[&str], title: Option<&str>, chars: &BoxChars) -> String {
+    let width = inner_width(lines, title);
+    let mut out = vec![top_border(
+        width,
+        title,
+        chars.top_left,
+        chars.top_right,
+        chars.horizontal,
+    )];
+
+    for line in lines {
+        out.push(format!(
+            "{v} {} {v}",
+            pad_right(line, width),
+            v = chars.vertical
+        ));
+    }
+
+    out.push(format!(
+        "{}{}{}",
+        chars.bottom_left,
+        chars.horizontal.repeat(width + 2),
+        chars.bottom_right
+    ));
+    out.join("\n")
+}
+
+fn draw_bubble(lines: &[&str], title: Option<&str>) -> String {
+    let width = inner_width(lines, title);
+    let top = top_border(width, title, " ", " ", "_");
+    let mut out = vec![top.trim_end().to_string()];
+
+    match lines {
+        [] => out.push(format!("< {} >", pad_right("", width))),
+        [only] => out.push(format!("< {} >", pad_right(only, width))),
+        _ => {
+            let last = lines.len() - 1;
+            for (i, line) in lines.iter().enumerate() {
+                let (l, r) = match i {
+                    0 => ('/', '\\'),
+                    i if i == last => ('\\', '/'),
+                    _ => ('|', '|'),
+                };
+                out.push(format!("{l} {} {r}", pad_right(line, width)));
+            }
+        }
+    }
+
+    out.push(format!(" {}", "-".repeat(width + 2)));
+    out.join("\n")
+}
@@ -0,0 +1,248 @@
+//! Template di output per personalizzare la resa di una citazione.
+
+use std::path::Path;
+
+/// Names accepted inside `{...}` placeholders and conditionals.
+pub const FIELDS: &[&str] = &["text", "author", "title", "source", "index", "quote"];
+
+/// Dati disponibili al template per una singola citazione
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    /// Quote body, without the trailing attribution line
+    pub text: String,
+    /// Attribution extracted from a trailing `— Name` line, if any
+    pub author: Option<String>,
+    /// Title of the fortune file (`# Title` on the first line)
+    pub title: Option<String>,
+    /// Path of the fortune file the quote comes from
+    pub source: Option<String>,
+    /// 1-based position of the quote within its file
+    pub index: Option<usize>,
+    /// Full quote exactly as stored in the file
+    pub quote: String,
+}
+
+impl TemplateContext {
+    /// Costruisce il contesto partendo dal testo grezzo della citazione
+    pub fn new(quote: &str) -> Self {
+        let (text, author) = split_attribution(quote);
+        TemplateContext {
+            text,
+            author,
+            quote: quote.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_title(mut self, title: Option<String>) -> Self {
+        self.title = title;
+        self
+    }
+
+    pub fn with_source(mut self, source: &Path) -> Self {
+        self.source = Some(source.display().to_string());
+        self
+    }
+
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    fn get(&self, field: &str) -> Option<String> {
+        match field {
+            "text" => Some(self.text.clone()),
+            "author" => self.author.clone(),
+            "title" => self.title.clone(),
+            "source" => self.source.clone(),
+            "index" => self.index.map(|i| i.to_string()),
+            "quote" => Some(self.quote.clone()),
+            _ => None,
+        }
+        .filter(|v| !v.is_empty())
+    }
+}
+
+/// Separa il corpo della citazione dalla riga finale di attribuzione.
+///
+/// The last line is treated as an attribution when it starts with an em dash,
+/// a horizontal bar or a double hyphen (`— Name`, `― Name`, `-- Name`).
+pub fn split_attribution(quote: &str) -> (String, Option<String>) {
+    let trimmed = quote.trim_end();
+
+    if let Some((body, last)) = trimmed.rsplit_once('\n') {
+        let last = last.trim();
+        for marker in ["—", "―", "--"] {
+            if let Some(author) = last.strip_prefix(marker) {
+                let author = author.trim();
+                if !author.is_empty() {
+                    return (body.trim_end().to_string(), Some(author.to_string()));
+                }
+            }
+        }
+    }
+
+    (trimmed.to_string(), None)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Literal(String),
+    Field(String),
+    Section {
+        field: String,
+        inverted: bool,
+        body: Vec<Node>,
+    },
+}
+
+/// Template già analizzato, pronto per essere applicato a più citazioni
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// Analizza la stringa del template e ne verifica la sintassi
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut stack: Vec<(String, bool, Vec<Node>)> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.char_indices().peekable();
+
+        while let Some((pos, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => literal.push('\n'),
+                    Some((_, 't')) => literal.push('\t'),
+                    Some((_, '\\')) => literal.push('\\'),
+                    Some((_, other)) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                '}' => {
+                    if matches!(chars.peek(), Some((_, '}'))) {
+                        chars.next();
+                        literal.push('}');
+                    } else {
+                        return Err(format!(
+                            "Unexpected '}}' at position {pos} (use '}}}}' for a literal brace)"
+                        ));
+                    }
+                }
+                '{' => {
+                    if matches!(chars.peek(), Some((_, '{'))) {
+                        chars.next();
+                        literal.push('{');
+                        continue;
+                    }
+
+                    let mut tag = String::new();
+                    let mut closed = false;
+                    for (_, t) in chars.by_ref() {
+                        if t == '}' {
+                            closed = true;
+                            break;
+                        }
+                        tag.push(t);
+                    }
+                    if !closed {
+                        return Err(format!("Unclosed '{{' at position {pos}"));
+                    }
+
+                    let current = match stack.last_mut() {
+                        Some((_, _, body)) => body,
+                        None => &mut nodes,
+                    };
+                    if !literal.is_empty() {
+                        current.push(Node::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    let tag = tag.trim();
+                    if let Some(name) = tag.strip_prefix('?') {
+                        stack.push((check_field(name.trim())?, false, Vec::new()));
+                    } else if let Some(name) = tag.strip_prefix('!') {
+                        stack.push((check_field(name.trim())?, true, Vec::new()));
+                    } else if let Some(name) = tag.strip_prefix('/') {
+                        let name = name.trim();
+                        let Some((field, inverted, body)) = stack.pop() else {
+                            return Err(format!(
+                                "Unexpected '{{/{name}}}' without an opening section"
+                            ));
+                        };
+                        if field != name {
+                            return Err(format!(
+                                "Mismatched section: '{{/{name}}}' closes '{{{}{field}}}'",
+                                if inverted { '!' } else { '?' }
+                            ));
+                        }
+                        let section = Node::Section {
+                            field,
+                            inverted,
+                            body,
+                        };
+                        match stack.last_mut() {
+                            Some((_, _, parent)) => parent.push(section),
+                            None => nodes.push(section),
+                        }
+                    } else {
+                        current.push(Node::Field(check_field(tag)?));
+                    }
+                }
+                _ => literal.push(c),
+            }
+        }
+
+        if let Some((field, _, _)) = stack.last() {
+            return Err(format!("Section '{field}' is never closed"));
+        }
+
+        if !literal.is_empty() {
+            nodes.push(Node::Literal(literal));
+        }
+
+        Ok(Template { nodes })
+    }
+
+    /// Applica il template ai dati della citazione
+    pub fn render(&self, ctx: &TemplateContext) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, ctx, &mut out);
+        out
+    }
+}
+
+fn check_field(name: &str) -> Result<String, String> {
+    if FIELDS.contains(&name) {
+        Ok(name.to_string())
+    } else {
+        Err(format!(
+            "Unknown placeholder '{name}'. Available: {}",
+            FIELDS.join(", ")
+        ))
+    }
+}
+
+fn render_nodes(nodes: &[Node], ctx: &TemplateContext, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Literal(s) => out.push_str(s),
+            Node::Field(name) => {
+                if let Some(value) = ctx.get(name) {
+                    out.push_str(&value);
+                }
+            }
+            Node::Section {
+                field,
+                inverted,
+                body,
+            } => {
+                if ctx.get(field).is_some() != *inverted {
+                    render_nodes(body, ctx, out);
+                }
+            }
+        }
+    }
+}
@@ -1,136 +1,317 @@
-use crate::config;
-use crate::config::Config;
-use crate::loader::FortuneFile;
-use crate::log::ConsoleLog;
-use anyhow::{Context, Result};
+use crate::config::{self, Config};
+use crate::context::AppContext;
+use crate::diagnostics::Diagnostic;
+use crate::error::{Error, Result};
+use crate::export::{self, Format};
+use crate::fortune::{Fortune, QuoteId, Selection};
+use crate::loader::{self, ParseOptions};
+use crate::selector::{
+    Candidate, Daily, NonRepeating, Policy, Selector, ShuffleBag, Uniform, Weighted,
+};
+use crate::source::{self, FortuneSource};
+use crate::style::{self, Character, Style};
+use crate::template::{Template, TemplateContext};
+use crate::wrap::WrapOptions;
 use fs2::FileExt;
 use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
-use std::{fs, io};
+use std::time::Duration;
 
-/// Estrae una citazione casuale dalla lista
-pub fn random_quote(quotes: &[String]) -> &str {
+/// Estrae una citazione casuale dalla lista (`None` se è vuota)
+pub fn random_quote(quotes: &[String]) -> Option<&str> {
     let mut rng = rand::rng();
-    quotes.choose(&mut rng).map(|s| s.as_str()).unwrap()
+    quotes.choose(&mut rng).map(|s| s.as_str())
 }
 
-/// Stampa una citazione casuale dal file fortune
-pub fn print_random_from_files(paths: &[&Path]) -> Result<(), String> {
-    let mut all_quotes: Vec<String> = Vec::new();
-    let mut origin_by_index: Vec<PathBuf> = Vec::new();
-
-    // 1) Carichiamo tutte le citazioni e tracciamo il file di origine
-    for path in paths {
-        match FortuneFile::from_file(path) {
-            Ok(f) => {
-                for q in &f.quotes {
-                    all_quotes.push(q.clone());
-                    origin_by_index.push(path.to_path_buf());
+/// Opzioni di selezione e di resa per la stampa della citazione
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    /// Custom output template (`--template` or `template` in the config)
+    pub template: Option<Template>,
+    /// Wrapping, indentation and centering
+    pub wrap: WrapOptions,
+    /// Frame drawn around the quote (`--style`)
+    pub style: Style,
+    /// Character speaking the quote in the `bubble` style
+    pub character: Character,
+    /// Show the fortune file title in the frame
+    pub print_title: bool,
+    /// Markdown/HTML output (`--format`); takes precedence over template and style
+    pub format: Format,
+    /// Print the originating file and the quote ordinal before the quote (`-c`)
+    pub show_source: bool,
+    /// Skip quotes shorter than this many characters
+    pub min_length: Option<usize>,
+    /// Skip quotes longer than this many characters
+    pub max_length: Option<usize>,
+    /// Relative weight of each fortune file, keyed by path (default 1, 0 disables the file)
+    pub weights: BTreeMap<String, f64>,
+    /// Avoid repeating the last quote of a file, and remember the new one
+    pub use_cache: bool,
+    /// Selection policy (`--selection`)
+    pub selection: Policy,
+    /// Delimiter and comment syntax of the fortune files
+    pub parse: ParseOptions,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions {
+            template: None,
+            wrap: WrapOptions::default(),
+            style: Style::default(),
+            character: Character::default(),
+            print_title: false,
+            format: Format::default(),
+            show_source: false,
+            min_length: None,
+            max_length: None,
+            weights: BTreeMap::new(),
+            use_cache: true,
+            selection: Policy::default(),
+            parse: ParseOptions::default(),
+        }
+    }
+}
+
+impl PrintOptions {
+    /// Selettore corrispondente alla politica, ai filtri di lunghezza e ai pesi.
+    ///
+    /// Sources with weight 0 are always excluded. With `use_cache`, `weighted`
+    /// and `uniform` avoid the last quote of each file and `shuffle` keeps its
+    /// bag in the cache directory; `daily` never looks at the cache.
+    pub fn selector(&self, ctx: &AppContext) -> Box<dyn Selector> {
+        let (min, max) = (self.min_length, self.max_length);
+        let weights = Weighted::new(self.weights.clone());
+        let filter = {
+            let weights = weights.clone();
+            move |c: &Candidate<'_>| {
+                if weights.weight_of(c.source_id) <= 0.0 {
+                    return false;
+                }
+                if min.is_none() && max.is_none() {
+                    return true;
+                }
+                // Il testo di ogni candidata viene letto e scartato subito
+                match c.text() {
+                    Ok(text) => {
+                        let len = text.chars().count();
+                        min.is_none_or(|min| len >= min) && max.is_none_or(|max| len <= max)
+                    }
+                    // Se non si legge, l'errore emerge quando la citazione viene scelta
+                    Err(_) => true,
                 }
             }
-            Err(e) => {
-                ConsoleLog::warn(format!("Could not load file {}: {e}", path.display()));
+        };
+
+        let cached = self.use_cache.then(|| ctx.clone());
+        let parse = self.parse.clone();
+        match (self.selection, cached) {
+            (Policy::Weighted, Some(ctx)) => {
+                Box::new(NonRepeating::cached_with(weights, ctx, parse).with_filter(filter))
+            }
+            (Policy::Weighted, None) => Box::new(weights.with_filter(filter)),
+            (Policy::Uniform, Some(ctx)) => {
+                Box::new(NonRepeating::cached_with(Uniform, ctx, parse).with_filter(filter))
             }
+            (Policy::Uniform, None) => Box::new(Uniform.with_filter(filter)),
+            (Policy::Shuffle, Some(ctx)) => Box::new(ShuffleBag::cached(ctx).with_filter(filter)),
+            (Policy::Shuffle, None) => Box::new(ShuffleBag::new().with_filter(filter)),
+            (Policy::Daily, _) => Box::new(Daily::today().with_filter(filter)),
         }
     }
+}
+
+/// Parametri della pausa di lettura dopo la stampa (`fortune -w`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaitOptions {
+    /// Reading speed used to compute the pause
+    pub chars_per_second: f64,
+    /// Shortest pause, even for one-liners
+    pub min: Duration,
+    /// Longest pause, even for very long quotes
+    pub max: Duration,
+}
 
-    if all_quotes.is_empty() {
-        ConsoleLog::ko("No quotes found in any of the fortune files.");
-        return Err("No quotes found.".into());
+impl Default for WaitOptions {
+    fn default() -> Self {
+        // Stessi valori del `fortune` BSD: 20 caratteri al secondo, almeno 6 secondi
+        WaitOptions {
+            chars_per_second: 20.0,
+            min: Duration::from_secs(6),
+            max: Duration::from_secs(60),
+        }
     }
+}
 
-    // 2) Proviamo a evitare ripetizioni dal *medesimo* file
-    // Carichiamo ultima citazione SOLO se deriva da uno dei file in uso
-    let mut last_quote = None;
+impl WaitOptions {
+    /// Durata della pausa per il testo indicato
+    pub fn duration_for(&self, text: &str) -> Duration {
+        let chars = text.chars().count() as f64;
+        let secs = if self.chars_per_second > 0.0 {
+            chars / self.chars_per_second
+        } else {
+            0.0
+        };
+        // Velocità minuscole darebbero pause oltre il limite di `Duration`
+        Duration::try_from_secs_f64(secs)
+            .unwrap_or(self.max)
+            .clamp(self.min, self.max.max(self.min))
+    }
+}
 
-    for p in paths {
-        if let Ok(q) = load_last_cache(p) {
-            last_quote = Some(q);
-            break; // basta la prima cache utile
-        }
+/// Sceglie una citazione casuale dai file fortune, applicando filtri, pesi e cache.
+///
+/// Each path is opened with [`source::open_source`] (fortune files, `strfile`
+/// indexes, directories, JSON/YAML). Files that cannot be loaded are skipped
+/// and reported in [`Selection::warnings`]; when none is usable, the error of
+/// the first one is returned. Nothing is printed: see [`render_fortune`].
+pub fn select_fortune(
+    ctx: &AppContext,
+    paths: &[&Path],
+    options: &PrintOptions,
+) -> Result<Selection> {
+    loader::check_stdin_once(paths)?;
+    let (sources, failures) = source::open_sources_with(paths, &options.parse);
+
+    // Se nessun file è utilizzabile l'errore è quello del primo file, gli altri sono avvisi
+    let mut failures = failures.into_iter();
+    if sources.iter().all(|s| s.is_empty()) {
+        return Err(failures.next().unwrap_or(Error::NoQuotes { path: None }));
     }
+    let warnings: Vec<Diagnostic> = failures
+        .map(|e| Diagnostic::warn(format!("Skipping fortune file: {e}")))
+        .collect();
 
-    let quote = if let Some(last) = last_quote {
-        // Rimuoviamo tutte le citazioni identiche all’ultima
-        let filtered: Vec<&String> = all_quotes.iter().filter(|q| *q != &last).collect();
+    let refs: Vec<&dyn FortuneSource> = sources.iter().map(|s| s.as_ref()).collect();
+    let mut selection = select_fortune_from(ctx, &refs, options)?;
+    selection.warnings.splice(0..0, warnings);
+    Ok(selection)
+}
 
-        if filtered.is_empty() {
-            // Se tutte erano uguali (caso rarissimo), scegliamo pure
-            all_quotes.choose(&mut rand::rng()).unwrap().clone()
-        } else {
-            filtered.choose(&mut rand::rng()).unwrap().to_string()
-        }
-    } else {
-        // Nessuna citazione precedente → scelta libera
-        all_quotes.choose(&mut rand::rng()).unwrap().clone()
-    };
+/// Sceglie una citazione da sorgenti qualsiasi, con la politica di `options`
+/// (vedi [`PrintOptions::selector`]).
+pub fn select_fortune_from(
+    ctx: &AppContext,
+    sources: &[&dyn FortuneSource],
+    options: &PrintOptions,
+) -> Result<Selection> {
+    let mut selector = options.selector(ctx);
+    select_fortune_with(sources, &mut selector)
+}
 
-    // 3) Identifichiamo il file da cui la citazione proviene
-    let idx = all_quotes
+/// Sceglie una citazione dalle sorgenti con il selettore indicato.
+///
+/// Returns [`Error::NoQuotes`] when the sources are empty and
+/// [`Error::NoMatchingQuotes`] when the selector rejects every quote.
+pub fn select_fortune_with(
+    sources: &[&dyn FortuneSource],
+    selector: &mut dyn Selector,
+) -> Result<Selection> {
+    // 1) Tutte le citazioni come (sorgente, posizione): il testo si legge solo se serve
+    let candidates: Vec<Candidate<'_>> = sources
         .iter()
-        .position(|q| q == &quote)
-        .expect("internal mismatch");
-    let origin = &origin_by_index[idx];
-
-    // 4) Stampa effettiva
-    println!("{quote}");
+        .flat_map(|source| (0..source.len()).map(|i| Candidate::from_source(*source, i)))
+        .collect();
+    if candidates.is_empty() {
+        return Err(Error::NoQuotes { path: None });
+    }
 
-    // 5) Salviamo la cache SOLO per il file di origine
-    if let Err(e) = save_last_cache(origin.as_path(), &quote) {
-        ConsoleLog::warn(format!("Could not update cache: {e}"));
+    // 2) Scelta delegata al selettore
+    let idx = selector
+        .select(&candidates)?
+        .ok_or(Error::NoMatchingQuotes)?;
+    let chosen = candidates[idx];
+
+    // 3) Testo della sola citazione scelta
+    let mut s = 0;
+    let mut i = idx;
+    while i >= sources[s].len() {
+        i -= sources[s].len();
+        s += 1;
     }
+    let text = chosen.text()?.into_owned();
+
+    let (meta, index) = sources[s].origin(i);
+    let fortune = Fortune {
+        text,
+        title: meta.title.clone(),
+        source: meta.path.clone().unwrap_or_else(|| PathBuf::from(&meta.id)),
+        index: index + 1,
+        preformatted: meta.preformatted,
+        id: QuoteId {
+            source_id: sources[s].metadata().id.clone(),
+            index: i,
+        },
+    };
 
-    Ok(())
+    Ok(Selection {
+        fortune,
+        warnings: Vec::new(),
+    })
 }
 
-/// Percorso del file cache per un determinato fortune file
-pub fn get_cache_path(dat_path: &Path) -> PathBuf {
-    let mut base = config::app_dir();
-    base.push("cache");
+/// Testo da stampare per la citazione, secondo formato, template e stile
+pub fn render_fortune(fortune: &Fortune, options: &PrintOptions) -> String {
+    let body = if options.format != Format::Text {
+        export::render_quote(&fortune.text, options.format)
+    } else {
+        let text = match &options.template {
+            Some(template) => {
+                let ctx = TemplateContext::new(&fortune.text)
+                    .with_title(fortune.title.clone())
+                    .with_source(&fortune.source)
+                    .with_index(fortune.index);
+                template.render(&ctx)
+            }
+            None => fortune.text.clone(),
+        };
+        let title = fortune.title.as_deref().filter(|_| options.print_title);
+        // Le citazioni preformattate (ASCII art, codice) non vanno a capo: solo rientro e centratura
+        let layout = WrapOptions {
+            width: options.wrap.width.filter(|_| !fortune.preformatted),
+            ..options.wrap.clone()
+        };
+        style::render(&text, title, options.style, options.character, &layout)
+    };
 
-    // ✅ garantisce che la directory esista sempre
-    if let Err(e) = fs::create_dir_all(&base) {
-        ConsoleLog::warn(format!("Unable to create cache directory: {e}"));
+    // `-c`: provenienza e separatore prima della citazione
+    if options.show_source {
+        format!("{}\n%\n{body}", source_line(&fortune.source, fortune.index))
+    } else {
+        body
     }
+}
 
-    let name = dat_path
-        .file_stem()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string()
-        + ".cache";
+/// Riga di provenienza stampata da `-c`, es. `(/path/to/file.fort #3)`
+pub fn source_line(path: &Path, ordinal: usize) -> String {
+    format!("({} #{ordinal})", path.display())
+}
 
-    base.push(name);
-    base
+/// Percorso del file cache per un determinato fortune file
+pub fn get_cache_path(ctx: &AppContext, dat_path: &Path) -> PathBuf {
+    ctx.paths.cache_file(dat_path)
 }
 
 /// Legge l’ultima citazione salvata in cache (se esiste)
 pub fn read_last_cache(path: &Path) -> Option<String> {
-    match fs::read_to_string(path) {
-        Ok(content) => {
-            ConsoleLog::info(format!("Loaded cached quote from '{}'.", path.display()));
-            Some(content)
-        }
-        Err(_) => None,
-    }
+    fs::read_to_string(path).ok()
 }
 
 /// Scrive l’ultima citazione nella cache
-pub fn write_last_cache(path: &Path, quote: &str) {
-    if let Err(e) = fs::write(path, quote) {
-        ConsoleLog::warn(format!(
-            "Failed to write cache file '{}': {e}",
-            path.display()
-        ));
-    }
+pub fn write_last_cache(path: &Path, quote: &str) -> Result<()> {
+    fs::write(path, quote).map_err(|e| Error::io(path, e))
 }
 
-/// Restituisce una citazione casuale diversa dalla precedente (se possibile)
-pub fn random_nonrepeating(quotes: &[String], last: Option<String>) -> &str {
+/// Restituisce una citazione casuale diversa dalla precedente (se possibile; `None` se la lista è vuota)
+pub fn random_nonrepeating(quotes: &[String], last: Option<String>) -> Option<&str> {
     let mut rng = rand::rng();
     let filtered: Vec<&String> = quotes
         .iter()
@@ -138,38 +319,27 @@ pub fn random_nonrepeating(quotes: &[String], last: Option<String>) -> &str {
         .collect();
 
     if filtered.is_empty() {
-        quotes.choose(&mut rng).unwrap()
+        random_quote(quotes)
     } else {
-        filtered.choose(&mut rng).unwrap()
+        filtered.choose(&mut rng).map(|s| s.as_str())
     }
 }
 
-/// Directory base della cache
-fn get_cache_dir() -> PathBuf {
-    let mut p = config::app_dir();
-    p.push("rfortune");
-    p.push("cache");
-    p
-}
-
-/// Svuota completamente la cache
-pub fn clear_cache_dir() -> io::Result<()> {
-    let dir = get_cache_dir();
-    if dir.exists() {
-        fs::remove_dir_all(&dir)?;
-        ConsoleLog::ok(format!("Cache directory cleared: {}", dir.display()));
-    } else {
-        ConsoleLog::info("Cache directory is already empty.");
+/// Svuota completamente la cache; ritorna `false` se non c'era nulla da cancellare
+pub fn clear_cache_dir(ctx: &AppContext) -> Result<bool> {
+    let dir = &ctx.paths.cache_dir;
+    if !dir.exists() {
+        return Ok(false);
     }
-    Ok(())
+    fs::remove_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+    Ok(true)
 }
 
 /// Ensure that the parent directory for the given cache store path exists.
 /// Returns an error if creation fails.
 fn ensure_cache_dir(store: &Path) -> Result<()> {
     if let Some(parent) = store.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("create cache dir: {}", parent.display()))?;
+        fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
     }
     Ok(())
 }
@@ -185,28 +355,57 @@ fn open_and_lock(store: &Path, exclusive: bool) -> Result<File> {
             .create(true)
             .truncate(true)
             .open(store)
-            .with_context(|| format!("open cache file: {}", store.display()))?
+            .map_err(|e| Error::io(store, e))?
     } else {
         OpenOptions::new()
             .read(true)
             .open(store)
-            .with_context(|| format!("open cache file (read): {}", store.display()))?
+            .map_err(|e| Error::io(store, e))?
     };
 
-    if exclusive {
+    let locked = if exclusive {
         file.lock_exclusive()
-            .with_context(|| format!("lock cache (exclusive): {}", store.display()))?;
     } else {
         file.lock_shared()
-            .with_context(|| format!("lock cache (shared): {}", store.display()))?;
-    }
+    };
+    locked.map_err(|source| Error::CacheLock {
+        path: store.to_path_buf(),
+        source,
+    })?;
 
     Ok(file)
 }
 
+/// Ultima citazione mostrata da una sorgente, come salvata nel file di cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastQuote {
+    pub id: QuoteId,
+    pub text: String,
+}
+
+impl LastQuote {
+    /// `true` se la candidata è questa citazione: stessa posizione e stesso testo.
+    ///
+    /// The text is read only when the position matches; a quote edited since
+    /// it was shown counts as a different one.
+    pub fn is(&self, candidate: &Candidate<'_>) -> bool {
+        self.id.source_id == candidate.source_id
+            && self.id.index == candidate.index
+            && candidate.text().is_ok_and(|text| text == self.text)
+    }
+}
+
 /// Salva l’ultima citazione usata in un file di cache (per-file, locking)
-pub fn save_last_cache(path: &Path, quote: &str) -> Result<()> {
-    let store = get_cache_path(path);
+pub fn save_last_cache(ctx: &AppContext, path: &Path, index: usize, quote: &str) -> Result<()> {
+    let store = get_cache_path(ctx, path);
+    let last = LastQuote {
+        id: QuoteId {
+            source_id: path.to_string_lossy().to_string(),
+            index,
+        },
+        text: quote.to_string(),
+    };
+    let data = serde_json::to_string(&last).unwrap_or_default();
 
     // Ensure parent exists
     ensure_cache_dir(&store)?;
@@ -215,10 +414,11 @@ pub fn save_last_cache(path: &Path, quote: &str) -> Result<()> {
     let mut file = open_and_lock(&store, true)?;
 
     // Truncate and write the quote atomically via the locked file handle
-    file.set_len(0)?;
-    file.seek(SeekFrom::Start(0))?;
-    file.write_all(quote.as_bytes())?;
-    file.sync_all()?;
+    file.set_len(0)
+        .and_then(|_| file.seek(SeekFrom::Start(0)))
+        .and_then(|_| file.write_all(data.as_bytes()))
+        .and_then(|_| file.sync_all())
+        .map_err(|e| Error::io(&store, e))?;
 
     // Release lock
     file.unlock().ok();
@@ -227,64 +427,146 @@ pub fn save_last_cache(path: &Path, quote: &str) -> Result<()> {
     Ok(())
 }
 
+/// Sostituisce `store` con `data` sotto il lock esclusivo di `<store>.lock`, via file temporaneo e rename
+pub(crate) fn replace_locked(store: &Path, data: &[u8]) -> Result<()> {
+    ensure_cache_dir(store)?;
+
+    let sibling = |suffix: &str| {
+        let mut name = store.file_name().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        store.with_file_name(name)
+    };
+    let lock = open_and_lock(&sibling(".lock"), true)?;
+
+    let tmp = sibling(&format!(".{}.tmp", std::process::id()));
+    let written = fs::write(&tmp, data)
+        .and_then(|_| fs::rename(&tmp, store))
+        .map_err(|e| Error::io(store, e));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+
+    lock.unlock().ok();
+    written
+}
+
 /// Carica l'ULTIMA citazione mostrata per il file `path` (per-file cache)
-/// Ritorna Ok(quote) se presente, Err(...) se assente o in caso di problema non critico.
-pub fn load_last_cache(path: &Path) -> Result<String> {
-    let store = get_cache_path(path);
+/// Ritorna Ok(Some(quote)) se presente, Ok(None) se non c'è ancora una cache.
+///
+/// Cache files written by older versions hold only the text: the position is
+/// then recovered from `path` only if the text is found there, see
+/// [`LastQuote`]. A cache written for another file with the same name is ignored.
+pub fn load_last_cache(ctx: &AppContext, path: &Path) -> Result<Option<LastQuote>> {
+    load_last_cache_with(ctx, path, &ParseOptions::default())
+}
+
+/// Come `load_last_cache`, rileggendo `path` con le opzioni indicate per le cache del vecchio formato
+pub fn load_last_cache_with(
+    ctx: &AppContext,
+    path: &Path,
+    options: &ParseOptions,
+) -> Result<Option<LastQuote>> {
+    let store = get_cache_path(ctx, path);
 
     // garantisci che la directory cache esista
-    if let Some(parent) = store.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("failed to create cache directory: {}", parent.display()))?;
-    }
+    ensure_cache_dir(&store)?;
 
-    // se il file non esiste ancora → ritorna "nessuna cache" ma senza errore fatale
+    // se il file non esiste ancora → "nessuna cache", non è un errore
     if !store.exists() {
-        return Err(anyhow::anyhow!("no cache"));
+        return Ok(None);
     }
 
     // Apri file in sola lettura e acquisisci lock condiviso
     let mut file = open_and_lock(&store, false)?;
 
     let mut data = String::new();
-    file.seek(SeekFrom::Start(0))?;
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| Error::io(&store, e))?;
     file.read_to_string(&mut data).ok();
 
     // Rilascia il lock (ignore unlock error)
     let _ = file.unlock();
 
-    Ok(data)
+    let source_id = path.to_string_lossy();
+    Ok(match serde_json::from_str::<LastQuote>(&data) {
+        Ok(last) if last.id.source_id == source_id => Some(last),
+        Ok(_) => None,
+        // Formato precedente: solo il testo, la posizione va ritrovata
+        Err(_) => legacy_last_quote(path, data, options),
+    })
 }
 
-pub fn ensure_app_initialized() -> io::Result<()> {
-    let dir = config::app_dir();
-
-    if dir.exists() {
-        return Ok(()); // tutto a posto
-    }
+/// Citazione di una cache in formato testo, se il testo compare ancora nel file
+fn legacy_last_quote(path: &Path, text: String, options: &ParseOptions) -> Option<LastQuote> {
+    let source = source::open_source_with(path, options).ok()?;
+    let index = (0..source.len()).find(|&i| {
+        source
+            .quote(i)
+            .ok()
+            .flatten()
+            .is_some_and(|q| q == text.as_str())
+    })?;
+    Some(LastQuote {
+        id: QuoteId {
+            source_id: path.to_string_lossy().to_string(),
+            index,
+        },
+        text,
+    })
+}
 
-    // Se il processo è interattivo (TTY), chiedi conferma
-    if atty::is(atty::Stream::Stdin) {
-        print!("Configuration directory not found. Initialize rFortune now? [Y/n]: ");
-        io::stdout().flush()?;
+/// Cosa fare quando la directory dell'applicazione non esiste ancora
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InitMode {
+    /// Let the caller ask the user (see `InitStatus::NeedsConfirmation`)
+    #[default]
+    Ask,
+    /// Initialize without asking (`--yes`)
+    Yes,
+    /// Never create files (`--no-init`)
+    Never,
+}
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let answer = input.trim().to_lowercase();
+/// Esito di `ensure_app_initialized`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitStatus {
+    /// The application directory already existed
+    Ready,
+    /// Configuration and default fortune file have just been created
+    Initialized,
+    /// Initialization was skipped because of `InitMode::Never`
+    Skipped,
+    /// The directory is missing and `InitMode::Ask` was given: call again with
+    /// `InitMode::Yes` once the user agrees
+    NeedsConfirmation,
+}
 
-        if answer == "n" || answer == "no" {
-            ConsoleLog::warn("Initialization aborted by user.");
-            std::process::exit(0);
+/// Verifica che la directory dell'applicazione esista, creandola se `mode` lo consente.
+/// Non chiede nulla all'utente e non termina il processo: la decisione spetta al chiamante.
+pub fn ensure_app_initialized(ctx: &AppContext, mode: InitMode) -> Result<InitStatus> {
+    // Su Linux configurazione e cache passano alle directory XDG
+    if mode != InitMode::Never {
+        for (from, to) in config::migrate_to_xdg_layout(ctx)? {
+            ctx.report(Diagnostic::info(format!(
+                "Moved {} → {}",
+                from.display(),
+                to.display()
+            )));
         }
-    } else {
-        ConsoleLog::info("Configuration directory missing — initializing automatically.");
     }
 
-    // Esegui l’inizializzazione completa
-    ConsoleLog::info("Initializing rFortune environment...");
-    config::init_config_file()?;
-    ConsoleLog::ok("rFortune initialized successfully.");
-    Ok(())
+    if ctx.paths.data_dir.exists() {
+        return Ok(InitStatus::Ready); // tutto a posto
+    }
+
+    match mode {
+        InitMode::Never => Ok(InitStatus::Skipped),
+        InitMode::Ask => Ok(InitStatus::NeedsConfirmation),
+        InitMode::Yes => {
+            config::init_config_file(ctx)?;
+            Ok(InitStatus::Initialized)
+        }
+    }
 }
 
 pub fn get_fortune_sources(cli_files: Option<Vec<String>>, config: &Config) -> Vec<String> {
@@ -0,0 +1,537 @@
+//! Validazione della configurazione con messaggi puntuali.
+
+use crate::config;
+use crate::context::AppContext;
+use crate::layers::{self, ConfigLayer, ConfigSource, KeyKind};
+use crate::migrate;
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Gravità di un problema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The configuration cannot be used as it is
+    Error,
+    /// The configuration works, but probably not as intended
+    Warning,
+    /// Nothing is wrong, but an action is suggested (e.g. `config migrate`)
+    Info,
+}
+
+/// Un problema trovato nella configurazione
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// File (or environment variable) where the problem was found
+    pub origin: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Issue {
+    fn new(severity: Severity, origin: impl Into<String>, message: impl Into<String>) -> Self {
+        Issue {
+            severity,
+            origin: origin.into(),
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    fn at(mut self, line: Option<usize>, column: Option<usize>) -> Self {
+        self.line = line;
+        self.column = column;
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.origin)?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+            if let Some(col) = self.column {
+                write!(f, ":{col}")?;
+            }
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Valida il contenuto di un file di configurazione
+pub fn validate_content(origin: &str, content: &str) -> Vec<Issue> {
+    if content.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let values: Mapping = match serde_yaml::from_str::<Value>(content) {
+        Ok(Value::Mapping(m)) => m,
+        Ok(Value::Null) => return Vec::new(),
+        Ok(_) => {
+            return vec![
+                Issue::new(
+                    Severity::Error,
+                    origin,
+                    "the configuration must be a mapping of `key: value` pairs",
+                )
+                .at(Some(1), Some(1)),
+            ];
+        }
+        Err(e) => {
+            let loc = e.location();
+            return vec![
+                Issue::new(Severity::Error, origin, format!("syntax error: {e}")).at(
+                    loc.as_ref().map(|l| l.line()),
+                    loc.as_ref().map(|l| l.column()),
+                ),
+            ];
+        }
+    };
+
+    let mut issues = validate_mapping(origin, &values, content);
+
+    let (line, column) = key_position(content, "version").unzip();
+    match migrate::schema_version(&values) {
+        Ok(v) if v < migrate::CURRENT_VERSION => issues.push(
+            Issue::new(
+                Severity::Info,
+                origin,
+                format!(
+                    "schema version {v} is outdated (current: {}); run `rfortune config migrate` to upgrade it",
+                    migrate::CURRENT_VERSION
+                ),
+            )
+            .at(line, column),
+        ),
+        Ok(v) if v > migrate::CURRENT_VERSION => issues.push(
+            Issue::new(
+                Severity::Error,
+                origin,
+                format!(
+                    "schema version {v} was written by a newer rFortune (supported: {}); upgrade rFortune to use this file",
+                    migrate::CURRENT_VERSION
+                ),
+            )
+            .at(line, column),
+        ),
+        Ok(_) => {}
+        Err(e) => issues.push(Issue::new(Severity::Error, origin, e.to_string()).at(line, column)),
+    }
+
+    issues
+}
+
+/// Valida le chiavi e i tipi di una mappa già analizzata (`content` serve per le posizioni)
+fn validate_mapping(origin: &str, values: &Mapping, content: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for (key, value) in values {
+        let Some(key) = key.as_str() else {
+            issues.push(Issue::new(
+                Severity::Error,
+                origin,
+                format!("keys must be strings, found {}", config::format_value(key)),
+            ));
+            continue;
+        };
+        if key == "version" {
+            continue; // verificata a parte, vedi `migrate::schema_version`
+        }
+        let (line, column) = key_position(content, key).unzip();
+
+        let Some(kind) = layers::key_kind(key) else {
+            issues.push(
+                Issue::new(
+                    Severity::Warning,
+                    origin,
+                    format!("unknown key '{key}'{}", suggestion(key)),
+                )
+                .at(line, column),
+            );
+            continue;
+        };
+
+        if let Err(msg) = check_value(key, kind, value) {
+            issues.push(Issue::new(Severity::Error, origin, msg).at(line, column));
+        } else if let Value::Mapping(profiles) = value
+            && kind == KeyKind::Profiles
+        {
+            issues.extend(validate_profiles(origin, profiles, content));
+        }
+    }
+
+    issues
+}
+
+/// Valida i profili: ciascuno è una mappa di chiavi ordinarie
+fn validate_profiles(origin: &str, profiles: &Mapping, content: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for (name, definition) in profiles {
+        let Some(name) = name.as_str() else {
+            issues.push(Issue::new(
+                Severity::Error,
+                origin,
+                format!(
+                    "profile names must be strings, found {}",
+                    config::format_value(name)
+                ),
+            ));
+            continue;
+        };
+        let (line, column) = nested_key_position(content, &["profiles", name]).unzip();
+
+        let settings = match definition {
+            Value::Mapping(m) => m,
+            Value::Null => continue,
+            _ => {
+                issues.push(
+                    Issue::new(
+                        Severity::Error,
+                        origin,
+                        format!("profile '{name}' must be a mapping of settings"),
+                    )
+                    .at(line, column),
+                );
+                continue;
+            }
+        };
+
+        for (key, value) in settings {
+            let Some(key) = key.as_str() else {
+                continue;
+            };
+            let (line, column) = nested_key_position(content, &["profiles", name, key]).unzip();
+
+            let result = if layers::NOT_IN_PROFILE.contains(&key) {
+                Err((
+                    Severity::Error,
+                    format!("'{key}' cannot be set inside a profile"),
+                ))
+            } else {
+                match layers::key_kind(key) {
+                    Some(kind) => check_value(key, kind, value).map_err(|m| (Severity::Error, m)),
+                    None => Err((
+                        Severity::Warning,
+                        format!("unknown key '{key}'{}", suggestion(key)),
+                    )),
+                }
+            };
+
+            if let Err((severity, msg)) = result {
+                issues.push(
+                    Issue::new(severity, origin, format!("profile '{name}': {msg}"))
+                        .at(line, column),
+                );
+            }
+        }
+    }
+
+    issues
+}
+
+/// Verifica che il valore corrisponda al tipo atteso dalla chiave
+fn check_value(key: &str, kind: KeyKind, value: &Value) -> Result<(), String> {
+    if value.is_null() {
+        return Ok(());
+    }
+
+    match (kind, value) {
+        (KeyKind::Str, Value::String(s)) => config::validate_str_value(key, s)
+            .map_err(|e| format!("invalid value for '{key}': {e}")),
+        (KeyKind::Bool, Value::Bool(_)) => Ok(()),
+        (KeyKind::Float, Value::Number(n)) => match n.as_f64() {
+            Some(f) if f >= 0.0 => Ok(()),
+            _ => Err(format!("'{key}' must be a non-negative number")),
+        },
+        (KeyKind::Int, Value::Number(n)) => match n.as_u64() {
+            Some(_) => Ok(()),
+            None => Err(format!("'{key}' must be a non-negative integer")),
+        },
+        (KeyKind::Map, Value::Mapping(map)) => {
+            for (file, weight) in map {
+                if !file.is_string() {
+                    return Err(format!(
+                        "'{key}' keys must be fortune file paths, found {}",
+                        config::format_value(file)
+                    ));
+                }
+                if !weight.as_f64().is_some_and(|w| w >= 0.0) {
+                    return Err(format!(
+                        "'{key}': the weight of '{}' must be a non-negative number",
+                        config::format_value(file)
+                    ));
+                }
+            }
+            Ok(())
+        }
+        (KeyKind::Profiles, Value::Mapping(_)) => Ok(()),
+        (KeyKind::List, Value::Sequence(items)) => match items.iter().find(|v| !v.is_string()) {
+            Some(bad) => Err(format!(
+                "'{key}' must be a list of paths, found {}",
+                config::format_value(bad)
+            )),
+            None => Ok(()),
+        },
+        (kind, other) => Err(format!(
+            "'{key}' expects {}, found {}",
+            describe_kind(kind),
+            describe_value(other)
+        )),
+    }
+}
+
+fn describe_kind(kind: KeyKind) -> &'static str {
+    match kind {
+        KeyKind::Str => "a string",
+        KeyKind::Bool => "a boolean (true/false)",
+        KeyKind::Float => "a number",
+        KeyKind::Int => "a non-negative integer",
+        KeyKind::List => "a list",
+        KeyKind::Map => "a mapping of file: weight",
+        KeyKind::Profiles => "a mapping of profile names",
+    }
+}
+
+fn describe_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("the string \"{s}\""),
+        Value::Bool(b) => format!("the boolean {b}"),
+        Value::Number(n) => format!("the number {n}"),
+        Value::Sequence(_) => "a list".to_string(),
+        Value::Mapping(_) => "a mapping".to_string(),
+        _ => "an unsupported value".to_string(),
+    }
+}
+
+/// Propone la chiave conosciuta più simile, per gli errori di battitura
+fn suggestion(key: &str) -> String {
+    layers::CONFIG_KEYS
+        .iter()
+        .map(|(k, _)| (*k, edit_distance(key, k)))
+        .filter(|(_, d)| *d <= 2)
+        .min_by_key(|(_, d)| *d)
+        .map(|(k, _)| format!(" (did you mean '{k}'?)"))
+        .unwrap_or_default()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            row.push((prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+
+    prev[b.len()]
+}
+
+/// Riga e colonna (1-based) di una chiave di primo livello nel testo YAML
+fn key_position(content: &str, key: &str) -> Option<(usize, usize)> {
+    content.lines().enumerate().find_map(|(i, line)| {
+        let rest = line
+            .strip_prefix(key)
+            .or_else(|| line.strip_prefix(&format!("\"{key}\"")))
+            .or_else(|| line.strip_prefix(&format!("'{key}'")))?;
+        rest.trim_start().starts_with(':').then_some((i + 1, 1))
+    })
+}
+
+/// Come `key_position`, per una chiave annidata (es. `["profiles", "work", "style"]`)
+fn nested_key_position(content: &str, path: &[&str]) -> Option<(usize, usize)> {
+    let mut start = 0;
+    let mut found = None;
+
+    for key in path {
+        let (i, col) = content
+            .lines()
+            .enumerate()
+            .skip(start)
+            .find_map(|(i, line)| {
+                let trimmed = line.trim_start();
+                let rest = trimmed
+                    .strip_prefix(key)
+                    .or_else(|| trimmed.strip_prefix(&format!("\"{key}\"")))
+                    .or_else(|| trimmed.strip_prefix(&format!("'{key}'")))?;
+                rest.trim_start()
+                    .starts_with(':')
+                    .then_some((i, line.len() - trimmed.len() + 1))
+            })?;
+        start = i + 1;
+        found = Some((i + 1, col));
+    }
+
+    found
+}
+
+/// Valida le variabili d'ambiente `RFORTUNE_*` riconosciute
+pub fn validate_env<I>(vars: I) -> Vec<Issue>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut issues = Vec::new();
+
+    for (name, raw) in vars {
+        let Some(key) = name.strip_prefix(layers::ENV_PREFIX) else {
+            continue;
+        };
+        let key = key.to_lowercase();
+        let Some(kind) = layers::key_kind(&key) else {
+            continue;
+        };
+
+        let expected = || format!("expects {}, found \"{raw}\"", describe_kind(kind));
+        let result = match kind {
+            KeyKind::Bool => layers::parse_bool(&raw).map(|_| ()).ok_or_else(expected),
+            KeyKind::Float => match raw.trim().parse::<f64>() {
+                Ok(f) if f >= 0.0 => Ok(()),
+                _ => Err(expected()),
+            },
+            KeyKind::Int => raw
+                .trim()
+                .parse::<u64>()
+                .map(|_| ())
+                .map_err(|_| expected()),
+            KeyKind::Str => config::validate_str_value(&key, &raw),
+            KeyKind::List => Ok(()),
+            KeyKind::Map | KeyKind::Profiles => Err(format!(
+                "'{key}' cannot be set from the environment; use a configuration file"
+            )),
+        };
+
+        if let Err(msg) = result {
+            issues.push(Issue::new(Severity::Error, name.clone(), msg));
+        }
+    }
+
+    issues
+}
+
+/// Segnala i file fortune configurati che non esistono
+pub fn validate_fortune_files(layers: &[ConfigLayer]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for (key, entry) in layers::effective_values(layers) {
+        let Some((value, source)) = entry else {
+            continue;
+        };
+        if key != "default_file" && key != "fortune_files" {
+            continue;
+        }
+
+        let paths: Vec<&str> = match &value {
+            Value::String(s) => vec![s.as_str()],
+            Value::Sequence(seq) => seq.iter().filter_map(Value::as_str).collect(),
+            _ => continue,
+        };
+
+        let origin = source_origin(&source);
+        let content = source.path().and_then(|p| fs::read_to_string(p).ok());
+        for p in paths {
+            if !Path::new(p).exists() {
+                let (line, column) = content
+                    .as_deref()
+                    .and_then(|c| key_position(c, key))
+                    .unzip();
+                issues.push(
+                    Issue::new(
+                        Severity::Warning,
+                        origin.clone(),
+                        format!("fortune file not found: {p} (from '{key}')"),
+                    )
+                    .at(line, column),
+                );
+            }
+        }
+    }
+
+    issues
+}
+
+fn source_origin(source: &ConfigSource) -> String {
+    match source {
+        ConfigSource::Env => "environment".to_string(),
+        ConfigSource::Profile(_) => source.to_string(),
+        _ => source
+            .path()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Valida tutti i livelli di configurazione attivi, incluso il profilo selezionato
+pub fn validate_all(
+    ctx: &AppContext,
+    explicit: Option<&Path>,
+    profile: Option<&str>,
+) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let files: Vec<PathBuf> = match explicit {
+        Some(p) => {
+            if !p.exists() {
+                issues.push(Issue::new(
+                    Severity::Error,
+                    p.display().to_string(),
+                    "configuration file not found",
+                ));
+            }
+            vec![p.to_path_buf()]
+        }
+        None => {
+            let mut v = vec![layers::system_config_path(), ctx.paths.config_file()];
+            if let Some(project) = std::env::current_dir()
+                .ok()
+                .and_then(|cwd| layers::find_project_config(&cwd))
+            {
+                v.push(project);
+            }
+            v
+        }
+    };
+
+    for path in files {
+        match fs::read_to_string(&path) {
+            Ok(content) => issues.extend(validate_content(&path.display().to_string(), &content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => issues.push(Issue::new(
+                Severity::Error,
+                path.display().to_string(),
+                format!("cannot read file: {e}"),
+            )),
+        }
+    }
+
+    issues.extend(validate_env(std::env::vars()));
+
+    // Profilo e file mancanti hanno senso solo se la configurazione è leggibile
+    if !issues.iter().any(Issue::is_error) {
+        let mut active = layers::config_layers(ctx, explicit);
+        if let Err(e) = layers::apply_profile(&mut active, profile) {
+            let origin = if profile.is_some() {
+                "--profile"
+            } else if std::env::var(layers::PROFILE_ENV).is_ok_and(|v| !v.trim().is_empty()) {
+                layers::PROFILE_ENV
+            } else {
+                "default_profile"
+            };
+            issues.push(Issue::new(Severity::Error, origin, e.to_string()));
+        }
+        issues.extend(validate_fortune_files(&active));
+    }
+
+    issues
+}
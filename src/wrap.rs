@@ -0,0 +1,157 @@
+//! Impaginazione del testo: a capo automatico, rientro e centratura.
+
+use terminal_size::{Width, terminal_size};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Opzioni di impaginazione
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WrapOptions {
+    /// Maximum line width in columns (indentation included); `None` disables wrapping
+    pub width: Option<usize>,
+    /// Number of spaces prepended to every line
+    pub indent: usize,
+    /// Center every line within `width`
+    pub center: bool,
+}
+
+impl WrapOptions {
+    /// `true` when the options would leave the text untouched
+    pub fn is_noop(&self) -> bool {
+        self.width.is_none() && self.indent == 0 && !self.center
+    }
+}
+
+/// Larghezza del terminale, se lo stdout è una TTY
+pub fn terminal_width() -> Option<usize> {
+    if !atty::is(atty::Stream::Stdout) {
+        return None;
+    }
+    terminal_size().map(|(Width(w), _)| w as usize)
+}
+
+/// Larghezza di visualizzazione di una stringa (in colonne)
+pub fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// Applica a capo, rientro e centratura al testo
+pub fn format_text(text: &str, opts: &WrapOptions) -> String {
+    if opts.is_noop() {
+        return text.to_string();
+    }
+
+    let content_width = opts.width.map(|w| w.saturating_sub(opts.indent).max(1));
+
+    let lines = match content_width {
+        Some(w) => wrap_text(text, w),
+        None => text.lines().map(str::to_string).collect(),
+    };
+
+    let block_width =
+        content_width.unwrap_or_else(|| lines.iter().map(|l| display_width(l)).max().unwrap_or(0));
+    let pad = " ".repeat(opts.indent);
+
+    lines
+        .iter()
+        .map(|line| {
+            if line.is_empty() {
+                return String::new();
+            }
+            let mut out = pad.clone();
+            if opts.center {
+                let free = block_width.saturating_sub(display_width(line));
+                out.push_str(&" ".repeat(free / 2));
+            }
+            out.push_str(line);
+            out
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Spezza il testo in righe di al più `width` colonne; le righe che ci stanno restano intatte
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    text.lines()
+        .flat_map(|line| wrap_line(line.trim_end(), width))
+        .collect()
+}
+
+/// Spezza una singola riga sulle parole, ripetendo il rientro; le parole troppo lunghe vengono divise
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    if display_width(line) <= width {
+        return vec![line.to_string()];
+    }
+
+    // Il rientro resta su ogni riga, purché lasci spazio al testo
+    let body = line.trim_start();
+    let indent = &line[..line.len() - body.len()];
+    let indent = if display_width(indent) < width {
+        indent
+    } else {
+        ""
+    };
+    let width = width - display_width(indent);
+
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut current_width = 0;
+
+    for word in body.split_whitespace() {
+        let word_width = display_width(word);
+        let sep = usize::from(!current.is_empty());
+
+        if current_width + sep + word_width <= width {
+            if sep == 1 {
+                current.push(' ');
+            }
+            current.push_str(word);
+            current_width += sep + word_width;
+            continue;
+        }
+
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+            current_width = 0;
+        }
+
+        if word_width <= width {
+            current.push_str(word);
+            current_width = word_width;
+        } else {
+            for piece in split_word(word, width) {
+                if !current.is_empty() {
+                    lines.push(std::mem::take(&mut current));
+                }
+                current_width = display_width(&piece);
+                current = piece;
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines.into_iter().map(|l| format!("{indent}{l}")).collect()
+}
+
+/// Divide una parola più larga di `width`, senza separare i caratteri combinanti
+fn split_word(word: &str, width: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_width = 0;
+
+    for c in word.chars() {
+        let w = c.width().unwrap_or(0);
+        if w > 0 && current_width + w > width && !current.is_empty() {
+            pieces.push(std::mem::take(&mut current));
+            current_width = 0;
+        }
+        current.push(c);
+        current_width += w;
+    }
+
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
@@ -1,21 +1,16 @@
-use rfortune::{config, utils};
+mod common;
+
+use common::sandbox;
+use rfortune::context::{AppContext, Paths};
+use rfortune::utils;
 
 #[test]
 fn cache_write_read_roundtrip() {
     // Crea una sandbox temporanea per la directory dell'app
-    let nanos = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    let mut tmp = std::env::temp_dir();
-    tmp.push(format!("rfortune_test_{}_{}", std::process::id(), nanos));
-
-    // Pulizia iniziale e creazione
-    let _ = std::fs::remove_dir_all(&tmp);
-    std::fs::create_dir_all(&tmp).expect("create tmp dir");
+    let tmp = sandbox("roundtrip");
 
-    // Imposta la directory di app per i test
-    config::set_app_dir_for_tests(tmp.clone());
+    // Contesto isolato con tutte le directory dell'app nella sandbox
+    let ctx = AppContext::new(Paths::in_dir(&tmp));
 
     // Crea un file sorgente che verrà usato come "fortune file"
     let source = tmp.join("source.dat");
@@ -23,10 +18,10 @@ fn cache_write_read_roundtrip() {
 
     // Salva una citazione nella cache e poi la rilegge
     let quote = "This is a test quote";
-    utils::save_last_cache(&source, quote).expect("save_last_cache failed");
+    utils::save_last_cache(&ctx, &source, 0, quote).expect("save_last_cache failed");
 
-    let loaded = utils::load_last_cache(&source).expect("load_last_cache failed");
-    assert_eq!(loaded, quote);
+    let loaded = utils::load_last_cache(&ctx, &source).expect("load_last_cache failed");
+    assert_eq!(loaded.map(|l| l.text).as_deref(), Some(quote));
 
     // Pulizia finale
     let _ = std::fs::remove_dir_all(&tmp);
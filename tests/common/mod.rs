@@ -0,0 +1,16 @@
+//! Funzioni di supporto condivise dai test di integrazione.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Directory temporanea vuota, distinta per test binario, nome e processo
+pub fn sandbox(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "rfortune_{}_{name}_{}",
+        env!("CARGO_CRATE_NAME"),
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
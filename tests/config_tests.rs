@@ -0,0 +1,154 @@
+mod common;
+
+use common::sandbox;
+use rfortune::config::{Config, format_value, load_config_file, migrate_layout};
+use serde_yaml::Value;
+use std::fs;
+
+#[test]
+fn test_set_and_get_typed_values() {
+    let mut cfg = Config::default();
+
+    cfg.set_value("print_title", &["no".to_string()], false)
+        .unwrap();
+    cfg.set_value("style", &["box".to_string()], false).unwrap();
+    cfg.set_value("wait_min_seconds", &["2.5".to_string()], false)
+        .unwrap();
+
+    assert_eq!(cfg.print_title, Some(false));
+    assert_eq!(cfg.style.as_deref(), Some("box"));
+    assert_eq!(cfg.wait_min_seconds, Some(2.5));
+    assert_eq!(
+        cfg.get_value("style").unwrap(),
+        Some(Value::String("box".into()))
+    );
+    assert_eq!(cfg.get_value("template").unwrap(), None);
+}
+
+#[test]
+fn test_set_rejects_invalid_values() {
+    let mut cfg = Config::default();
+
+    assert!(cfg.set_value("nope", &["x".to_string()], false).is_err());
+    assert!(
+        cfg.set_value("use_cache", &["maybe".to_string()], false)
+            .is_err()
+    );
+    assert!(
+        cfg.set_value("style", &["fancy".to_string()], false)
+            .is_err()
+    );
+    assert!(
+        cfg.set_value("template", &["{unknown}".to_string()], false)
+            .is_err()
+    );
+    assert!(
+        cfg.set_value("wait_max_seconds", &["-1".to_string()], false)
+            .is_err()
+    );
+    assert!(
+        cfg.set_value("style", &["box".to_string(), "ascii".to_string()], false)
+            .is_err()
+    );
+    assert!(cfg.set_value("style", &["box".to_string()], true).is_err());
+    assert_eq!(cfg, Config::default());
+}
+
+#[test]
+fn test_set_list_replace_and_append() {
+    let mut cfg = Config::default();
+
+    cfg.set_value(
+        "fortune_files",
+        &["a.fort".to_string(), "b.fort".to_string()],
+        false,
+    )
+    .unwrap();
+    cfg.set_value("fortune_files", &["c.fort".to_string()], true)
+        .unwrap();
+    assert_eq!(cfg.fortune_files, vec!["a.fort", "b.fort", "c.fort"]);
+
+    cfg.set_value("fortune_files", &["d.fort".to_string()], false)
+        .unwrap();
+    assert_eq!(cfg.fortune_files, vec!["d.fort"]);
+    assert_eq!(
+        format_value(&cfg.get_value("fortune_files").unwrap().unwrap()),
+        "[d.fort]"
+    );
+}
+
+#[test]
+fn test_save_to_and_load_config_file() {
+    let dir = sandbox("save_to");
+    let path = dir.join("nested").join("custom.conf");
+
+    // Un file assente equivale a una configurazione vuota
+    assert_eq!(load_config_file(&path).unwrap(), Config::default());
+
+    let mut cfg = Config::default();
+    cfg.set_value("format", &["markdown".to_string()], false)
+        .unwrap();
+    cfg.save_to(&path).unwrap();
+
+    let loaded = load_config_file(&path).unwrap();
+    assert_eq!(loaded.format.as_deref(), Some("markdown"));
+
+    fs::write(&path, "style: [unclosed").unwrap();
+    assert!(load_config_file(&path).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_set_weights_and_lengths() {
+    let mut cfg = Config::default();
+
+    cfg.set_value("weights", &["a.fort=3".to_string()], false)
+        .unwrap();
+    cfg.set_value("weights", &["b.fort=0.5".to_string()], true)
+        .unwrap();
+    cfg.set_value("max_length", &["160".to_string()], false)
+        .unwrap();
+
+    assert_eq!(cfg.weights.get("a.fort"), Some(&3.0));
+    assert_eq!(cfg.weights.get("b.fort"), Some(&0.5));
+    assert_eq!(cfg.max_length, Some(160));
+    assert!(
+        cfg.set_value("weights", &["a.fort".to_string()], false)
+            .is_err()
+    );
+    assert!(
+        cfg.set_value("profiles", &["x".to_string()], false)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_migrate_legacy_layout_to_split_dirs() {
+    let root = sandbox("layout");
+    let legacy = root.join("data");
+    let config_dir = root.join("config");
+    let cache_dir = root.join("cache");
+    fs::create_dir_all(legacy.join("cache")).unwrap();
+    fs::write(legacy.join("rfortune.conf"), "print_title: true\n").unwrap();
+    fs::write(legacy.join("rfortune.dat"), "Quote\n").unwrap();
+    fs::write(legacy.join("cache").join("rfortune.cache"), "Quote").unwrap();
+
+    let moved = migrate_layout(&legacy, &config_dir, &cache_dir).unwrap();
+
+    assert_eq!(moved.len(), 2);
+    assert!(config_dir.join("rfortune.conf").exists());
+    assert!(cache_dir.join("rfortune.cache").exists());
+    assert!(!legacy.join("rfortune.conf").exists());
+    // I file fortune restano nella directory dei dati
+    assert!(legacy.join("rfortune.dat").exists());
+
+    // Una seconda esecuzione non fa nulla
+    assert!(
+        migrate_layout(&legacy, &config_dir, &cache_dir)
+            .unwrap()
+            .is_empty()
+    );
+
+    let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::sandbox;
+use rfortune::config;
+use rfortune::context::{AppContext, Paths};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_paths_in_dir_layout() {
+    let paths = Paths::in_dir("/opt/rfortune");
+
+    assert_eq!(
+        paths.config_file(),
+        Path::new("/opt/rfortune/rfortune.conf")
+    );
+    assert_eq!(
+        paths.default_fortune_file(),
+        Path::new("/opt/rfortune/rfortune.dat")
+    );
+    assert_eq!(
+        paths.cache_file(Path::new("/data/murphy.fort")),
+        Path::new("/opt/rfortune/cache/murphy.cache")
+    );
+}
+
+#[test]
+fn test_contexts_are_isolated() {
+    let first = AppContext::new(Paths::in_dir(sandbox("first")));
+    let second = AppContext::new(Paths::in_dir(sandbox("second")));
+
+    config::init_config_file(&first).unwrap();
+    config::init_default_file(&first).unwrap();
+
+    assert!(first.paths.config_file().exists());
+    assert!(first.paths.default_fortune_file().exists());
+    assert!(!second.paths.config_file().exists());
+    assert!(config::load_config(&first).is_some());
+    assert!(config::load_config(&second).is_none());
+
+    let _ = fs::remove_dir_all(&first.paths.data_dir);
+    let _ = fs::remove_dir_all(&second.paths.data_dir);
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use common::sandbox;
+use rfortune::Error;
+use rfortune::context::{AppContext, Paths};
+use rfortune::diagnostics::Diagnostic;
+use rfortune::editor::{
+    AfterError, EditOutcome, edit_validated, editor_command, parse_editor, resolve_editor_with,
+};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_parse_editor_with_arguments() {
+    assert_eq!(
+        parse_editor("emacsclient -t").unwrap(),
+        ("emacsclient".to_string(), vec!["-t".to_string()])
+    );
+    assert_eq!(
+        parse_editor("\"/opt/My Editor/bin/edit\" --new-window").unwrap(),
+        (
+            "/opt/My Editor/bin/edit".to_string(),
+            vec!["--new-window".to_string()]
+        )
+    );
+    assert!(parse_editor("   ").is_err());
+    assert!(parse_editor("vim 'unterminated").is_err());
+}
+
+#[test]
+fn test_gui_editors_wait_for_the_file() {
+    let path = Path::new("rfortune.conf");
+
+    let cmd = editor_command("code", path).unwrap();
+    let args: Vec<_> = cmd.get_args().collect();
+    assert_eq!(cmd.get_program(), "code");
+    assert_eq!(args, vec!["--wait", "rfortune.conf"]);
+
+    // L'opzione non viene duplicata
+    let cmd = editor_command("code --wait", path).unwrap();
+    assert_eq!(cmd.get_args().count(), 2);
+
+    let cmd = editor_command("vi", path).unwrap();
+    assert_eq!(cmd.get_args().collect::<Vec<_>>(), vec!["rfortune.conf"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_invalid_edit_is_restored_when_not_interactive() {
+    let dir = sandbox("restore");
+    let path = dir.join("notes.txt");
+    fs::write(&path, "good\n").unwrap();
+
+    let check = |c: &[u8]| {
+        if String::from_utf8_lossy(c).contains("bad") {
+            Err(vec!["bad content".to_string()])
+        } else {
+            Ok(())
+        }
+    };
+
+    // I messaggi della libreria arrivano al chiamante, non alla console
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&messages);
+    let ctx = AppContext::new(Paths::in_dir(&dir))
+        .with_diagnostics(move |d: &Diagnostic| sink.lock().unwrap().push(d.message.clone()));
+
+    let mut reported = Vec::new();
+    let restore = |errors: &[String]| {
+        reported.extend_from_slice(errors);
+        Ok(AfterError::Restore)
+    };
+    let outcome =
+        edit_validated(&ctx, "sh -c 'echo bad >> \"$1\"' sh", &path, check, restore).unwrap();
+    assert_eq!(outcome, EditOutcome::Restored);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "good\n");
+    assert_eq!(reported, vec!["bad content".to_string()]);
+    assert!(
+        messages
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|m| m.contains("previous version"))
+    );
+
+    let outcome = edit_validated(
+        &ctx,
+        "sh -c 'echo fine >> \"$1\"' sh",
+        &path,
+        check,
+        |_: &[String]| Ok(AfterError::Restore),
+    )
+    .unwrap();
+    assert_eq!(outcome, EditOutcome::Valid);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "good\nfine\n");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_empty_editor_variables_are_skipped() {
+    let env = |visual: &'static str, editor: &'static str| {
+        move |name: &str| match name {
+            "VISUAL" => Some(visual.to_string()),
+            "EDITOR" => Some(editor.to_string()),
+            _ => None,
+        }
+    };
+
+    assert_eq!(resolve_editor_with(None, env("", "vim")), "vim");
+    assert_eq!(resolve_editor_with(None, env("code -w", "vim")), "code -w");
+    assert_eq!(
+        resolve_editor_with(Some(" ".to_string()), env("  ", "emacs")),
+        "emacs"
+    );
+    assert_eq!(
+        resolve_editor_with(Some("hx".to_string()), env("", "")),
+        "hx"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_file_deleted_in_editor_is_an_error() {
+    let dir = sandbox("deleted");
+    let path = dir.join("notes.txt");
+    fs::write(&path, "good\n").unwrap();
+    let ctx = AppContext::new(Paths::in_dir(&dir));
+
+    let result = edit_validated(
+        &ctx,
+        "sh -c 'rm \"$1\"' sh",
+        &path,
+        |_: &[u8]| Ok(()),
+        |_: &[String]| Ok(AfterError::Keep),
+    );
+    assert!(matches!(result, Err(Error::Io { .. })));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_file_edit_accepts_latin1_files() {
+    let dir = sandbox("latin1");
+    let path = dir.join("latin1.fort");
+    fs::write(&path, b"Caff\xe8 corretto\n%\n").unwrap();
+
+    // L'editor aggiunge un'altra citazione in Latin-1
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rfortune"))
+        .args(["--yes", "file", "edit"])
+        .arg(&path)
+        .args([
+            "--editor",
+            "sh -c 'printf \"Citt\\351\\n%%\\n\" >> \"$1\"' sh",
+        ])
+        .env("RFORTUNE_HOME", dir.join("home"))
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read(&path).unwrap(),
+        b"Caff\xe8 corretto\n%\nCitt\xe9\n%\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::sandbox;
+use rfortune::export::{
+    Format, escape_html, escape_markdown, export_files, render_file, render_quote,
+};
+use rfortune::loader::FortuneFile;
+use std::fs;
+use std::path::Path;
+
+fn sample_file() -> FortuneFile {
+    FortuneFile {
+        title: Some("Programming".to_string()),
+        quotes: vec![
+            "Premature optimization is the root of all evil.\n— Donald Knuth".to_string(),
+            "In Rust we trust.".to_string(),
+        ],
+    }
+}
+
+#[test]
+fn test_quote_markdown_blockquote() {
+    let md = render_quote(
+        "Roses are red,\nviolets are blue.\n— Anonymous",
+        Format::Markdown,
+    );
+    assert_eq!(
+        md,
+        "> Roses are red,\\\n> violets are blue.\n>\n> — *Anonymous*"
+    );
+}
+
+#[test]
+fn test_quote_markdown_fence_outlasts_backticks() {
+    let md = render_quote("    ```\n    code\n    ````", Format::Markdown);
+    assert_eq!(
+        md,
+        "> `````text\n>     ```\n>     code\n>     ````\n> `````"
+    );
+
+    let plain = render_quote("    let x = 1;", Format::Markdown);
+    assert!(plain.starts_with("> ```text\n"));
+}
+
+#[test]
+fn test_quote_html_figure() {
+    let html = render_quote("1 < 2 & \"3\"\n— Math", Format::Html);
+    assert!(html.contains("<p>1 &lt; 2 &amp; &quot;3&quot;</p>"));
+    assert!(html.contains("<figcaption>— <cite>Math</cite></figcaption>"));
+}
+
+#[test]
+fn test_render_file_markdown() {
+    let md = render_file(&sample_file(), "fallback", Format::Markdown);
+    assert!(md.starts_with("# Programming\n\n"));
+    assert!(md.contains("> — *Donald Knuth*"));
+    assert!(md.contains("\n\n---\n\n> In Rust we trust."));
+}
+
+#[test]
+fn test_render_file_uses_fallback_title() {
+    let mut file = sample_file();
+    file.title = None;
+    let html = render_file(&file, "dev", Format::Html);
+    assert!(html.contains("<h1>dev</h1>"));
+    assert_eq!(html.matches("<blockquote>").count(), 2);
+}
+
+#[test]
+fn test_text_export_roundtrip() {
+    let dir = sandbox("roundtrip");
+    let path = dir.join("roundtrip.fort");
+
+    fs::write(
+        &path,
+        render_file(&sample_file(), "roundtrip", Format::Text),
+    )
+    .unwrap();
+    let loaded = FortuneFile::from_file(&path).unwrap();
+    assert_eq!(loaded.title, sample_file().title);
+    assert_eq!(loaded.quotes, sample_file().quotes);
+
+    let doc = export_files(&[Path::new(&path)], Format::Html).unwrap();
+    assert!(doc.starts_with("<!DOCTYPE html>"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_format_parsing_and_escaping() {
+    assert_eq!("md".parse::<Format>().unwrap(), Format::Markdown);
+    assert_eq!("HTML".parse::<Format>().unwrap(), Format::Html);
+    assert!("pdf".parse::<Format>().is_err());
+    assert_eq!(escape_html("<a href='x'>"), "&lt;a href=&#39;x&#39;&gt;");
+
+    // Un numero seguito da `.` o `)` a inizio riga aprirebbe un elenco numerato
+    assert_eq!(escape_markdown("1. First"), "1\\. First");
+    assert_eq!(escape_markdown("  42) answer"), "  42\\) answer");
+    assert_eq!(escape_markdown("3."), "3\\.");
+    assert_eq!(escape_markdown("1.5 litres"), "1.5 litres");
+    assert_eq!(escape_markdown("In 1984. Orwell"), "In 1984. Orwell");
+    assert_eq!(
+        render_quote("2) Never give up\n— Someone", Format::Markdown),
+        "> 2\\) Never give up\n>\n> — *Someone*"
+    );
+}
+
+#[test]
+fn test_text_export_escapes_delimiter_lines() {
+    let file = FortuneFile {
+        title: None,
+        quotes: vec!["Before\n%\nafter".to_string()],
+    };
+
+    let text = render_file(&file, "escaped", Format::Text);
+    assert_eq!(text, "%\nBefore\n\\%\nafter\n%\n");
+    assert_eq!(FortuneFile::parse(&text).unwrap().quotes, file.quotes);
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use common::sandbox;
+use rfortune::Error;
+use rfortune::context::{AppContext, Paths};
+use rfortune::layers::{
+    ConfigLayer, ConfigSource, apply_profile, config_layers, env_layer, find_project_config,
+    merge_layers,
+};
+use serde_yaml::Mapping;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn layer(source: ConfigSource, yaml: &str) -> ConfigLayer {
+    ConfigLayer {
+        source,
+        values: serde_yaml::from_str::<Mapping>(yaml).unwrap(),
+    }
+}
+
+#[test]
+fn test_merge_overrides_individual_keys() {
+    let layers = vec![
+        layer(
+            ConfigSource::System(PathBuf::from("/etc/rfortune/rfortune.conf")),
+            "print_title: false\nuse_cache: false\nstyle: box\n",
+        ),
+        layer(
+            ConfigSource::User(PathBuf::from("user.conf")),
+            "print_title: true\nfortune_files: [a.fort, b.fort]\n",
+        ),
+        layer(ConfigSource::Env, "style: bubble\n"),
+    ];
+
+    let cfg = merge_layers(&layers).unwrap();
+    assert_eq!(cfg.print_title, Some(true));
+    assert_eq!(cfg.use_cache, Some(false));
+    assert_eq!(cfg.style.as_deref(), Some("bubble"));
+    assert_eq!(cfg.fortune_files, vec!["a.fort", "b.fort"]);
+}
+
+#[test]
+fn test_default_file_replaces_inherited_list() {
+    let layers = vec![
+        layer(
+            ConfigSource::User(PathBuf::from("user.conf")),
+            "fortune_files: [a.fort]\n",
+        ),
+        layer(
+            ConfigSource::Project(PathBuf::from(".rfortune.conf")),
+            "default_file: team.fort\n",
+        ),
+    ];
+
+    let cfg = merge_layers(&layers).unwrap();
+    assert!(cfg.fortune_files.is_empty());
+    assert_eq!(cfg.default_file.as_deref(), Some("team.fort"));
+}
+
+#[test]
+fn test_env_layer_parsing() {
+    let list = std::env::join_paths(["one.fort", "two.fort"]).unwrap();
+    let vars = vec![
+        ("RFORTUNE_PRINT_TITLE".to_string(), "no".to_string()),
+        ("RFORTUNE_TEMPLATE".to_string(), "{text}".to_string()),
+        ("RFORTUNE_WAIT_MIN_SECONDS".to_string(), "2.5".to_string()),
+        (
+            "RFORTUNE_FORTUNE_FILES".to_string(),
+            list.to_string_lossy().to_string(),
+        ),
+        ("RFORTUNE_UNKNOWN".to_string(), "x".to_string()),
+        ("RFORTUNE_USE_CACHE".to_string(), "maybe".to_string()),
+        ("HOME".to_string(), "/home/user".to_string()),
+    ];
+
+    let values = env_layer(vars);
+    assert_eq!(values.len(), 4);
+
+    let cfg = merge_layers(&[ConfigLayer {
+        source: ConfigSource::Env,
+        values,
+    }])
+    .unwrap();
+    assert_eq!(cfg.print_title, Some(false));
+    assert_eq!(cfg.template.as_deref(), Some("{text}"));
+    assert_eq!(cfg.wait_min_seconds, Some(2.5));
+    assert_eq!(cfg.fortune_files, vec!["one.fort", "two.fort"]);
+    assert!(cfg.use_cache.is_none());
+}
+
+#[test]
+fn test_find_project_config_upward() {
+    let root = sandbox("project");
+    let nested = root.join("src").join("deep");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(root.join(".rfortune.conf"), "style: ascii\n").unwrap();
+
+    assert_eq!(
+        find_project_config(&nested),
+        Some(root.join(".rfortune.conf"))
+    );
+
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_explicit_config_replaces_file_layers() {
+    let dir = sandbox("explicit");
+    let path = dir.join("custom.conf");
+    fs::write(&path, "fortune_files: [custom.fort]\n").unwrap();
+
+    let ctx = AppContext::new(Paths::in_dir(&dir));
+    let layers = config_layers(&ctx, Some(Path::new(&path)));
+    assert_eq!(layers[0].source, ConfigSource::Explicit(path.clone()));
+    assert!(
+        layers
+            .iter()
+            .all(|l| matches!(l.source, ConfigSource::Explicit(_) | ConfigSource::Env))
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_profile_overrides_files_but_not_env() {
+    let mut layers = vec![
+        layer(
+            ConfigSource::User(PathBuf::from("user.conf")),
+            "fortune_files: [a.fort]\nstyle: box\nformat: text\ndefault_profile: bot\n\
+             profiles:\n  bot:\n    fortune_files: [bot.fort]\n    format: markdown\n    style: ascii\n",
+        ),
+        layer(ConfigSource::Env, "style: bubble\n"),
+    ];
+
+    let applied = apply_profile(&mut layers, None).unwrap();
+
+    assert_eq!(applied.as_deref(), Some("bot"));
+    assert_eq!(layers[1].source, ConfigSource::Profile("bot".into()));
+    let cfg = merge_layers(&layers).unwrap();
+    assert_eq!(cfg.fortune_files, vec!["bot.fort"]);
+    assert_eq!(cfg.format.as_deref(), Some("markdown"));
+    assert_eq!(cfg.style.as_deref(), Some("bubble"));
+}
+
+#[test]
+fn test_profiles_merge_by_name_across_layers() {
+    let mut layers = vec![
+        layer(
+            ConfigSource::User(PathBuf::from("user.conf")),
+            "profiles:\n  ide:\n    max_length: 100\n",
+        ),
+        layer(
+            ConfigSource::Project(PathBuf::from(".rfortune.conf")),
+            "profiles:\n  team:\n    min_length: 10\n",
+        ),
+    ];
+
+    assert_eq!(merge_layers(&layers).unwrap().profiles.len(), 2);
+
+    apply_profile(&mut layers, Some("ide")).unwrap();
+    assert_eq!(merge_layers(&layers).unwrap().max_length, Some(100));
+}
+
+#[test]
+fn test_unknown_profile_is_an_error() {
+    let mut layers = vec![layer(
+        ConfigSource::User(PathBuf::from("user.conf")),
+        "profiles:\n  ide: {}\n",
+    )];
+
+    let err = apply_profile(&mut layers, Some("chat")).unwrap_err();
+    assert!(matches!(err, Error::Config(_)));
+    assert!(err.to_string().contains("Available: ide"));
+    assert_eq!(layers.len(), 1);
+}
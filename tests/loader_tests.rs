@@ -1,17 +1,14 @@
-use rfortune::config::set_app_dir_for_tests;
-use rfortune::loader::FortuneFile;
+mod common;
+
+use common::sandbox;
+use rfortune::Error;
+use rfortune::encoding::TextEncoding;
+use rfortune::loader::{Delimiter, FortuneFile, ParseOptions};
+use rfortune::source::{FortuneSource, MappedSource};
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-fn setup_test_env() -> PathBuf {
-    let sandbox = std::env::temp_dir().join("rfortune_test_env_loader");
-    println!("Setting up test sandbox at {:?}", sandbox);
-    fs::create_dir_all(&sandbox).unwrap();
-    set_app_dir_for_tests(sandbox.clone());
-    sandbox
-}
-
 fn create_temp_file(sandbox: &Path, content: &str, filename: &str) -> PathBuf {
     let path = sandbox.join(filename);
     let mut file = File::create(&path).expect("Failed to create temp file");
@@ -22,7 +19,7 @@ fn create_temp_file(sandbox: &Path, content: &str, filename: &str) -> PathBuf {
 
 #[test]
 fn test_loader_with_title() {
-    let sandbox = setup_test_env();
+    let sandbox = sandbox("loader_with_title");
 
     let data = "# Murphy's Law\n%\nIf something can go wrong, it will.\n%\nLeft to themselves, things tend to go from bad to worse.";
     let path = create_temp_file(&sandbox, data, "murphy_test.dat");
@@ -35,7 +32,7 @@ fn test_loader_with_title() {
 
 #[test]
 fn test_loader_without_title() {
-    let sandbox = setup_test_env();
+    let sandbox = sandbox("loader_without_title");
 
     let data = "%\nQuote one.\n%\nQuote two.";
     let path = create_temp_file(&sandbox, data, "notitle_test.dat");
@@ -47,12 +44,380 @@ fn test_loader_without_title() {
 
 #[test]
 fn test_loader_empty_file() {
-    let sandbox = setup_test_env();
+    let sandbox = sandbox("loader_empty_file");
 
     let data = "";
     let path = create_temp_file(&sandbox, data, "empty_test.dat");
     let result = FortuneFile::from_file(&path);
 
-    assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), "No quotes found in the file.");
+    match result {
+        Err(Error::NoQuotes { path: Some(p) }) => assert_eq!(p, path),
+        other => panic!("expected NoQuotes, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_loader_missing_file_is_io_error() {
+    let sandbox = sandbox("loader_missing_file_is_io_error");
+
+    let path = sandbox.join("does_not_exist.dat");
+    match FortuneFile::from_file(&path) {
+        Err(Error::Io { path: p, source }) => {
+            assert_eq!(p, path);
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        }
+        other => panic!("expected Io, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_loader_invalid_utf8_reports_line() {
+    let sandbox = sandbox("loader_invalid_utf8_reports_line");
+
+    let path = sandbox.join("latin1_test.dat");
+    fs::write(&path, b"First quote.\n%\nCaf\xe9\n").unwrap();
+
+    // Con la codifica imposta a UTF-8 non c'è ripiego su Latin-1
+    let options = ParseOptions {
+        encoding: TextEncoding::Utf8,
+        ..Default::default()
+    };
+    match FortuneFile::from_file_with(&path, &options) {
+        Err(err @ Error::Parse { line: Some(3), .. }) => {
+            assert!(err.to_string().contains("latin1_test.dat:3"));
+        }
+        other => panic!("expected Parse at line 3, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_loader_from_str() {
+    let fortune_file = FortuneFile::parse("# Embedded\n%\nOne\n%\nTwo\n%\n").unwrap();
+    assert_eq!(fortune_file.title.as_deref(), Some("Embedded"));
+    assert_eq!(fortune_file.quotes, ["One", "Two"]);
+
+    let parsed: FortuneFile = "Only one".parse().unwrap();
+    assert_eq!(parsed.quotes, ["Only one"]);
+
+    assert!(matches!(
+        FortuneFile::parse("%\n%\n"),
+        Err(Error::NoQuotes { .. })
+    ));
+}
+
+#[test]
+fn test_loader_from_readers() {
+    let data: &[u8] = b"%\nFrom a reader\n%\nSecond\n";
+
+    let from_read = FortuneFile::from_reader(data).unwrap();
+    let from_buf = FortuneFile::from_buf_reader(std::io::Cursor::new(data)).unwrap();
+    assert_eq!(from_read.quotes, ["From a reader", "Second"]);
+    assert_eq!(from_buf.quotes, from_read.quotes);
+
+    // Gli errori riportano il nome della sorgente indicato
+    match FortuneFile::from_named_reader(&b"\n\n#: x\n%\nok\n"[..], Path::new("api:quotes")) {
+        Err(err @ Error::Parse { line: Some(3), .. }) => {
+            assert!(err.to_string().starts_with("api:quotes:3"));
+        }
+        other => panic!("expected Parse at line 3, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_loader_custom_delimiters() {
+    let options = ParseOptions {
+        delimiter: "%%".parse().unwrap(),
+        ..Default::default()
+    };
+    let file =
+        FortuneFile::parse_with("%%\nFirst\n%\nstill first\n%%\nSecond\n%%", &options).unwrap();
+    assert_eq!(file.quotes, ["First\n%\nstill first", "Second"]);
+
+    let options = ParseOptions {
+        delimiter: Delimiter::BlankLine,
+        ..Default::default()
+    };
+    let file =
+        FortuneFile::parse_with("# Paragraphs\n\nOne\nline two\n\n\n  \nTwo\n", &options).unwrap();
+    assert_eq!(file.title.as_deref(), Some("Paragraphs"));
+    assert_eq!(file.quotes, ["One\nline two", "Two"]);
+
+    assert_eq!("blank".parse::<Delimiter>(), Ok(Delimiter::BlankLine));
+    assert!("".parse::<Delimiter>().is_err());
+    assert!("#".parse::<Delimiter>().is_err());
+}
+
+#[test]
+fn test_loader_comments_and_escapes() {
+    let text =
+        "# Title\n%\n# only a comment\n%\nKept\n# dropped\nline\n%\n\\%\n\\# not a comment\n%\n";
+
+    // Senza commenti, le righe `#` fanno parte delle citazioni
+    let plain = FortuneFile::parse(text).unwrap();
+    assert_eq!(plain.title.as_deref(), Some("Title"));
+    assert_eq!(
+        plain.quotes,
+        [
+            "# only a comment",
+            "Kept\n# dropped\nline",
+            "%\n\\# not a comment"
+        ]
+    );
+
+    let options = ParseOptions {
+        comments: true,
+        ..Default::default()
+    };
+    let commented = FortuneFile::parse_with(text, &options).unwrap();
+    assert_eq!(commented.title.as_deref(), Some("Title"));
+    assert_eq!(commented.quotes, ["Kept\nline", "%\n# not a comment"]);
+}
+
+#[test]
+fn test_loader_header_directives() {
+    let text = "#: delimiter = %%\n# Directives\n#: comments = on\n%%\nA\n# note\n%\n%%\n#: not a directive here\n";
+    let file = FortuneFile::parse(text).unwrap();
+    assert_eq!(file.title.as_deref(), Some("Directives"));
+    assert_eq!(file.quotes, ["A\n%"]);
+
+    match FortuneFile::parse("#: delimiter = %%\n#: colour = red\n%%\nA\n") {
+        Err(err @ Error::Parse { line: Some(2), .. }) => {
+            assert!(err.to_string().contains("Unknown directive 'colour'"));
+        }
+        other => panic!("expected Parse at line 2, got {other:?}"),
+    }
+    assert!(matches!(
+        FortuneFile::parse("#: comments = maybe\n%\nA\n"),
+        Err(Error::Parse { line: Some(1), .. })
+    ));
+}
+
+#[test]
+fn test_loader_hash_escape_only_where_needed() {
+    // Senza commenti `\#` è testo qualsiasi: il backslash resta
+    let code = "%\n#define DEBUG\n\\#include <stdio.h>\n%\n";
+    assert_eq!(
+        FortuneFile::parse(code).unwrap().quotes,
+        ["#define DEBUG\n\\#include <stdio.h>"]
+    );
+
+    // Nell'intestazione `\#:` protegge una riga che sarebbe una direttiva
+    let header = "\\#: not a directive\nstill the first quote\n%\n\\#: kept as is\n%\n";
+    let file = FortuneFile::parse(header).unwrap();
+    assert_eq!(
+        file.quotes,
+        [
+            "#: not a directive\nstill the first quote",
+            "\\#: kept as is"
+        ]
+    );
+
+    let sandbox = sandbox("loader_hash_escape_only_where_needed");
+    let path = create_temp_file(&sandbox, header, "escaped_header.fort");
+    let source = MappedSource::open(&path).unwrap();
+    assert_eq!(source.quote(0).unwrap().unwrap(), file.quotes[0]);
+    assert_eq!(source.quote(1).unwrap().unwrap(), file.quotes[1]);
+}
+
+/// ASCII art dalle raccolte classiche di fortune: indentazione e righe vuote contano
+const ASCII_ART: &str = r#"%
+        ___________________________
+       |                           |
+       |     Kilroy was here.      |
+       |___________________________|
+              \
+               \   ^__^
+                \  (oo)\_______
+                   (__)\       )\/\
+                       ||----w |
+                       ||     ||
+%
+    int main(void)
+    {
+
+        return 0;
+    }
+%
+	"Tis the voice of the Lobster: I heard him declare,
+You have baked me too brown, I must sugar my hair."
+		-- Lewis Carroll
+%
+"#;
+
+#[test]
+fn test_loader_preserves_whitespace() {
+    let options = ParseOptions {
+        preserve_whitespace: true,
+        ..Default::default()
+    };
+    let file = FortuneFile::parse_with(ASCII_ART, &options).unwrap();
+
+    // Il testo tra due delimitatori resta identico, senza l'ultimo a capo
+    let expected: Vec<&str> = ASCII_ART
+        .split("\n%\n")
+        .map(|q| q.trim_start_matches("%\n"))
+        .filter(|q| !q.is_empty())
+        .collect();
+    assert_eq!(file.quotes, expected);
+    assert!(file.quotes[0].starts_with("        ____"));
+    assert!(file.quotes[1].contains("{\n\n        return"));
+    assert!(file.quotes[2].starts_with("\t\"Tis"));
+
+    // Per default le citazioni vengono ripulite come prima
+    let trimmed = FortuneFile::parse(ASCII_ART).unwrap();
+    assert!(trimmed.quotes[0].starts_with("____"));
+    assert_eq!(trimmed.quotes[1], expected[1].trim());
+}
+
+#[test]
+fn test_loader_preserve_whitespace_directive() {
+    let text = "#: preserve_whitespace = on\n# Art\n%\n  /\\\n /  \\\n\n%\n   \n%\n";
+    let file = FortuneFile::parse(text).unwrap();
+    assert_eq!(file.title.as_deref(), Some("Art"));
+    // Le citazioni fatte solo di spazi restano escluse
+    assert_eq!(file.quotes, ["  /\\\n /  \\\n"]);
+
+    let options = ParseOptions {
+        preserve_whitespace: true,
+        comments: true,
+        ..Default::default()
+    };
+    let file = FortuneFile::parse_with("%\n  # note\n  \\%\n  x  \n%\n", &options).unwrap();
+    assert_eq!(file.quotes, ["  %\n  x  "]);
+}
+
+#[test]
+fn test_loader_preserve_whitespace_keeps_leading_blank_lines() {
+    let options = ParseOptions {
+        preserve_whitespace: true,
+        ..Default::default()
+    };
+    let sandbox = sandbox("loader_preserve_whitespace_keeps_leading_blank_lines");
+
+    // Prima citazione con o senza `%` iniziale, dopo un titolo o all'inizio del file
+    for (name, text) in [
+        ("leading_delim.fort", "%\n\n  first\n%\n\n  second\n%\n"),
+        ("no_delim.fort", "\n  first\n%\n\n  second\n"),
+        ("title.fort", "\n# Title\n\n  first\n%\n\n  second\n%\n"),
+    ] {
+        let file = FortuneFile::parse_with(text, &options).unwrap();
+        assert_eq!(file.quotes, ["\n  first", "\n  second"], "{name}");
+
+        let path = create_temp_file(&sandbox, text, name);
+        let source = MappedSource::open_with(&path, &options).unwrap();
+        assert_eq!(source.quote(0).unwrap().unwrap(), file.quotes[0], "{name}");
+        assert_eq!(source.quote(1).unwrap().unwrap(), file.quotes[1], "{name}");
+    }
+
+    // Le righe vuote prima delle direttive appartengono all'intestazione
+    let file = FortuneFile::parse("\n#: preserve_whitespace = on\n\nfirst\n%\n").unwrap();
+    assert_eq!(file.quotes, ["\nfirst"]);
+}
+
+#[test]
+fn test_loader_latin1_fallback() {
+    let sandbox = sandbox("loader_latin1_fallback");
+    let path = sandbox.join("legacy_latin1.dat");
+    fs::write(
+        &path,
+        b"# Citt\xe0\n%\nCaf\xe9 cr\xe8me\n%\n\x93Smart quotes\x94\n%\n",
+    )
+    .unwrap();
+
+    // Senza indicazioni si ripiega su Windows-1252 (che include Latin-1)
+    let file = FortuneFile::from_file(&path).unwrap();
+    assert_eq!(file.title.as_deref(), Some("Città"));
+    assert_eq!(file.quotes, ["Café crème", "\u{201c}Smart quotes\u{201d}"]);
+
+    let options = ParseOptions {
+        encoding: TextEncoding::Latin1,
+        ..Default::default()
+    };
+    let file = FortuneFile::from_file_with(&path, &options).unwrap();
+    assert_eq!(file.quotes[1], "\u{93}Smart quotes\u{94}");
+}
+
+#[test]
+fn test_loader_detects_bom_and_utf16() {
+    let text = "# Título\r\n%\r\nOlá, mundo\r\n%\r\n";
+    let utf16le: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let utf16be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+
+    let inputs = [
+        [b"\xEF\xBB\xBF".as_slice(), text.as_bytes()].concat(),
+        [b"\xFF\xFE".as_slice(), &utf16le].concat(),
+        [b"\xFE\xFF".as_slice(), &utf16be].concat(),
+        // UTF-16 senza BOM, come lo salvano alcuni editor
+        utf16le.clone(),
+    ];
+    for bytes in &inputs {
+        let file = FortuneFile::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(file.title.as_deref(), Some("Título"));
+        assert_eq!(file.quotes, ["Olá, mundo"]);
+    }
+    assert_eq!(TextEncoding::detect(&utf16le), TextEncoding::Utf16Le);
+    assert_eq!(TextEncoding::detect(b"plain"), TextEncoding::Utf8);
+}
+
+#[test]
+fn test_loader_encoding_directive() {
+    // La direttiva vale per il file anche se la configurazione dice altro
+    let bytes = b"#: encoding = latin1\n%\nNa\xefve\n%\n";
+    let options = ParseOptions {
+        encoding: TextEncoding::Utf8,
+        ..Default::default()
+    };
+    let file = FortuneFile::read_with(&bytes[..], Path::new("<test>"), &options).unwrap();
+    assert_eq!(file.quotes, ["Naïve"]);
+
+    assert!(matches!(
+        FortuneFile::parse("#: encoding = ebcdic\n%\nA\n"),
+        Err(Error::Parse { line: Some(1), .. })
+    ));
+    for name in TextEncoding::NAMES {
+        assert_eq!(name.parse::<TextEncoding>().unwrap().to_string(), *name);
+    }
+    assert_eq!("CP1252".parse(), Ok(TextEncoding::Windows1252));
+}
+
+#[test]
+fn test_loader_forced_encoding_is_used_up_front() {
+    let latin1 = ParseOptions {
+        encoding: TextEncoding::Latin1,
+        ..Default::default()
+    };
+    let name = Path::new("<test>");
+
+    // UTF-8 valido, ma la configurazione impone Latin-1
+    let file = FortuneFile::read_with(&b"%\nCaf\xc3\xa9\n%\n"[..], name, &latin1).unwrap();
+    assert_eq!(file.quotes, ["Caf\u{c3}\u{a9}"]);
+
+    // Sembra UTF-16 (un byte nullo ogni due): letto come tale avrebbe una direttiva sconosciuta
+    let utf16: Vec<u8> = "#: colour = red\n%\nA\n"
+        .encode_utf16()
+        .flat_map(|u| u.to_le_bytes())
+        .collect();
+    assert!(matches!(
+        FortuneFile::read_with(&utf16[..], name, &ParseOptions::default()),
+        Err(Error::Parse { line: Some(1), .. })
+    ));
+    let file = FortuneFile::read_with(&utf16[..], name, &latin1).unwrap();
+    assert!(file.title.is_some_and(|t| t.starts_with("\0:\0")));
+    assert_eq!(file.quotes.len(), 1);
+}
+
+#[test]
+fn test_loader_crlf_line_endings() {
+    let text = "# Windows\r\n%\r\nFirst line\r\nsecond line\r\n%\r\n  Indented\r\n%\r\n";
+    let file = FortuneFile::parse(text).unwrap();
+    assert_eq!(file.title.as_deref(), Some("Windows"));
+    assert_eq!(file.quotes, ["First line\nsecond line", "Indented"]);
+
+    let options = ParseOptions {
+        preserve_whitespace: true,
+        ..Default::default()
+    };
+    let file = FortuneFile::parse_with(text, &options).unwrap();
+    assert_eq!(file.quotes, ["First line\nsecond line", "  Indented"]);
+    assert!(file.quotes.iter().all(|q| !q.contains('\r')));
 }
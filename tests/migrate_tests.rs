@@ -0,0 +1,163 @@
+mod common;
+
+use common::sandbox;
+use rfortune::Error;
+use rfortune::config::load_config_file;
+use rfortune::context::{AppContext, Paths};
+use rfortune::layers::config_layers;
+use rfortune::migrate::{
+    CURRENT_VERSION, MigrationReport, backup_path, migrate_file, schema_version, upgrade,
+};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::process::Command;
+
+const LEGACY: &str = "default_file: /tmp/a.fort\nprint_title: true\n";
+
+#[test]
+fn test_upgrade_from_v0() {
+    let mut values: Mapping = serde_yaml::from_str(LEGACY).unwrap();
+    assert_eq!(schema_version(&values).unwrap(), 0);
+
+    let steps = upgrade(&mut values).unwrap();
+
+    assert_eq!(steps.len(), 1);
+    assert_eq!(schema_version(&values).unwrap(), CURRENT_VERSION);
+    assert_eq!(
+        values.get("fortune_files"),
+        Some(&Value::Sequence(vec![Value::String("/tmp/a.fort".into())]))
+    );
+    // Un file già aggiornato non cambia
+    assert!(upgrade(&mut values).unwrap().is_empty());
+}
+
+#[test]
+fn test_upgrade_keeps_existing_list() {
+    let mut values: Mapping =
+        serde_yaml::from_str("default_file: a.fort\nfortune_files: [b.fort]\n").unwrap();
+    upgrade(&mut values).unwrap();
+    assert_eq!(
+        values.get("fortune_files"),
+        Some(&Value::Sequence(vec![Value::String("b.fort".into())]))
+    );
+}
+
+#[test]
+fn test_newer_version_is_rejected() {
+    let mut values: Mapping = serde_yaml::from_str("version: 99\n").unwrap();
+    assert!(upgrade(&mut values).is_err());
+}
+
+#[test]
+fn test_newer_config_file_is_rejected_everywhere() {
+    let dir = sandbox("newer");
+    let path = dir.join("rfortune.conf");
+    let content = format!("version: {}\nprint_title: false\n", CURRENT_VERSION + 1);
+    fs::write(&path, &content).unwrap();
+
+    assert!(matches!(load_config_file(&path), Err(Error::Config(_))));
+    assert!(matches!(migrate_file(&path, true), Err(Error::Config(_))));
+
+    // Non viene nemmeno applicato come livello di configurazione
+    let ctx = AppContext::new(Paths::in_dir(&dir));
+    assert!(config_layers(&ctx, Some(&path)).is_empty());
+
+    // `config set` fallisce senza toccare il file
+    let output = Command::new(env!("CARGO_BIN_EXE_rfortune"))
+        .args(["--yes", "--config"])
+        .arg(&path)
+        .args(["config", "set", "print_title", "true"])
+        .env("RFORTUNE_HOME", &dir)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("newer"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), content);
+}
+
+#[test]
+fn test_dry_run_does_not_write() {
+    let dir = sandbox("dry");
+    let path = dir.join("rfortune.conf");
+    fs::write(&path, LEGACY).unwrap();
+
+    let report = migrate_file(&path, true).unwrap().expect("needs migration");
+
+    assert_eq!((report.from, report.to), (0, CURRENT_VERSION));
+    assert!(report.backup.is_none());
+    assert!(report.diff().contains(&"+ version: 1".to_string()));
+    assert_eq!(fs::read_to_string(&path).unwrap(), LEGACY);
+    assert!(!backup_path(&path, 0).exists());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_migration_writes_backup() {
+    let dir = sandbox("write");
+    let path = dir.join("rfortune.conf");
+    fs::write(&path, LEGACY).unwrap();
+
+    let report = migrate_file(&path, false)
+        .unwrap()
+        .expect("needs migration");
+
+    let backup = report.backup.expect("backup must be created");
+    assert_eq!(backup, dir.join("rfortune.conf.v0.bak"));
+    assert_eq!(fs::read_to_string(&backup).unwrap(), LEGACY);
+    assert!(
+        fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("version: 1\n")
+    );
+
+    // La seconda esecuzione non ha nulla da fare
+    assert!(migrate_file(&path, false).unwrap().is_none());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_diff_shows_reordered_and_duplicated_lines() {
+    let report = MigrationReport {
+        path: "rfortune.conf".into(),
+        from: 0,
+        to: 1,
+        steps: Vec::new(),
+        old_content: "a: 1\nb: 2\n".to_string(),
+        new_content: "b: 2\na: 1\nb: 2\n".to_string(),
+        backup: None,
+    };
+
+    assert_eq!(report.diff(), vec!["+ b: 2", "  a: 1", "  b: 2"]);
+
+    let swapped = MigrationReport {
+        new_content: "b: 2\na: 1\n".to_string(),
+        ..report
+    };
+    let diff = swapped.diff();
+    assert!(diff.iter().any(|l| l.starts_with("- ")));
+    assert!(diff.iter().any(|l| l.starts_with("+ ")));
+}
+
+#[test]
+fn test_migration_keeps_existing_backup() {
+    let dir = sandbox("keep_backup");
+    let path = dir.join("rfortune.conf");
+    fs::write(&path, LEGACY).unwrap();
+    fs::write(backup_path(&path, 0), "older backup\n").unwrap();
+
+    let report = migrate_file(&path, false)
+        .unwrap()
+        .expect("needs migration");
+
+    let backup = report.backup.expect("backup must be created");
+    assert_eq!(backup, dir.join("rfortune.conf.v0.1.bak"));
+    assert_eq!(fs::read_to_string(&backup).unwrap(), LEGACY);
+    assert_eq!(
+        fs::read_to_string(backup_path(&path, 0)).unwrap(),
+        "older backup\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,257 @@
+mod common;
+
+use common::sandbox;
+use rfortune::Error;
+use rfortune::context::{AppContext, Paths};
+use rfortune::selector::{
+    Candidate, Daily, NonRepeating, Policy, Selector, ShuffleBag, Uniform, Weighted,
+};
+use rfortune::source::{FortuneSource, MemorySource, SourceMetadata};
+use rfortune::utils::{PrintOptions, select_fortune_from, select_fortune_with};
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+
+const TEXTS: [&str; 5] = ["one", "two", "three", "four", "five"];
+
+fn candidates(source_id: &str) -> Vec<Candidate<'_>> {
+    TEXTS
+        .iter()
+        .enumerate()
+        .map(|(index, text)| Candidate::new(source_id, index, text))
+        .collect()
+}
+
+#[test]
+fn test_policy_names() {
+    for name in Policy::NAMES {
+        let policy: Policy = name.parse().unwrap();
+        assert_eq!(policy.to_string(), *name);
+    }
+    assert!("sometimes".parse::<Policy>().is_err());
+}
+
+#[test]
+fn test_empty_candidates_select_nothing() {
+    assert_eq!(Uniform.select(&[]).unwrap(), None);
+    assert_eq!(Weighted::default().select(&[]).unwrap(), None);
+    assert_eq!(ShuffleBag::new().select(&[]).unwrap(), None);
+    assert_eq!(Daily::for_day(1).select(&[]).unwrap(), None);
+}
+
+#[test]
+fn test_weighted_ignores_zero_weight_sources() {
+    let mut list = candidates("a");
+    list.extend(candidates("b"));
+    let mut selector = Weighted::new(BTreeMap::from([("a".to_string(), 0.0)]));
+
+    for _ in 0..50 {
+        let i = selector.select(&list).unwrap().unwrap();
+        assert_eq!(list[i].source_id, "b");
+    }
+}
+
+#[test]
+fn test_weighted_chooses_the_source_by_weight() {
+    // "a" ha una sola citazione, "b" cinque: il peso vale per il file, non per la citazione
+    let mut list = vec![Candidate::new("a", 0, "only")];
+    list.extend(candidates("b"));
+    let mut selector = Weighted::new(BTreeMap::from([("a".to_string(), 3.0)]));
+
+    let from_a = (0..4000)
+        .filter(|_| list[selector.select(&list).unwrap().unwrap()].source_id == "a")
+        .count();
+    // Atteso 3/4
+    assert!((2700..=3300).contains(&from_a), "{from_a} of 4000 from a");
+}
+
+#[test]
+fn test_non_repeating_history() {
+    let list = candidates("a");
+    let mut selector = NonRepeating::new(Uniform, 4);
+
+    // Con una storia di 4 su 5 citazioni, ogni gruppo di 5 scelte consecutive è senza ripetizioni
+    let picks: Vec<usize> = (0..20)
+        .map(|_| selector.select(&list).unwrap().unwrap())
+        .collect();
+    for window in picks.windows(5) {
+        let distinct: BTreeSet<&usize> = window.iter().collect();
+        assert_eq!(distinct.len(), 5);
+    }
+}
+
+#[test]
+fn test_shuffle_bag_deals_every_quote_once() {
+    let list = candidates("a");
+    let mut bag = ShuffleBag::new();
+
+    for _ in 0..3 {
+        let round: BTreeSet<usize> = (0..TEXTS.len())
+            .map(|_| bag.select(&list).unwrap().unwrap())
+            .collect();
+        assert_eq!(round.len(), TEXTS.len());
+    }
+}
+
+#[test]
+fn test_shuffle_bag_persists_in_cache() {
+    let sandbox = sandbox("bag");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+    let list = candidates("a");
+
+    // Un nuovo sacchetto per ogni scelta, come in esecuzioni separate della CLI
+    let round: BTreeSet<usize> = (0..TEXTS.len())
+        .map(|_| {
+            ShuffleBag::cached(ctx.clone())
+                .select(&list)
+                .unwrap()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(round.len(), TEXTS.len());
+}
+
+#[test]
+fn test_shuffle_bag_concurrent_saves_keep_the_file_valid() {
+    let sandbox = sandbox("bag_concurrent");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            let ctx = ctx.clone();
+            scope.spawn(move || {
+                let list = candidates("a");
+                for _ in 0..10 {
+                    ShuffleBag::cached(ctx.clone()).select(&list).unwrap();
+                }
+            });
+        }
+    });
+
+    let store = ctx.paths.cache_dir.join("shuffle-bag.json");
+    let saved = std::fs::read_to_string(&store).unwrap();
+    assert!(serde_json::from_str::<serde_json::Value>(&saved).is_ok());
+    let leftovers = std::fs::read_dir(&ctx.paths.cache_dir)
+        .unwrap()
+        .filter(|e| {
+            e.as_ref()
+                .unwrap()
+                .path()
+                .to_string_lossy()
+                .ends_with(".tmp")
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+}
+
+#[test]
+fn test_daily_is_deterministic() {
+    let list = candidates("a");
+    let today = Daily::for_day(20_000).select(&list).unwrap();
+    assert_eq!(Daily::for_day(20_000).select(&list).unwrap(), today);
+
+    let days: BTreeSet<usize> = (0..30)
+        .map(|d| Daily::for_day(d).select(&list).unwrap().unwrap())
+        .collect();
+    assert!(days.len() > 1);
+}
+
+#[test]
+fn test_filters_compose_with_selectors() {
+    let list = candidates("a");
+    let mut selector =
+        ShuffleBag::new().with_filter(|c: &Candidate<'_>| c.text().unwrap().len() == 3);
+
+    let picks: BTreeSet<String> = (0..10)
+        .map(|_| {
+            let chosen = list[selector.select(&list).unwrap().unwrap()];
+            chosen.text().unwrap().into_owned()
+        })
+        .collect();
+    assert_eq!(
+        picks,
+        BTreeSet::from(["one".to_string(), "two".to_string()])
+    );
+
+    let mut none = Uniform.with_filter(|_: &Candidate<'_>| false);
+    assert_eq!(none.select(&list).unwrap(), None);
+}
+
+#[test]
+fn test_select_fortune_with_explicit_selector() {
+    let source = MemorySource::new("db", TEXTS.iter().map(|t| t.to_string()).collect());
+    let sources: [&dyn FortuneSource; 1] = [&source];
+
+    let mut daily = Daily::for_day(42);
+    let first = select_fortune_with(&sources, &mut daily).unwrap();
+    let again = select_fortune_with(&sources, &mut daily).unwrap();
+    assert_eq!(first.fortune, again.fortune);
+
+    let mut nothing = Uniform.with_filter(|c: &Candidate<'_>| c.text().unwrap().is_empty());
+    assert!(matches!(
+        select_fortune_with(&sources, &mut nothing),
+        Err(Error::NoMatchingQuotes)
+    ));
+}
+
+#[test]
+fn test_print_options_selection_policy() {
+    let sandbox = sandbox("policy");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+    let source = MemorySource::new("db", TEXTS.iter().map(|t| t.to_string()).collect());
+
+    let options = PrintOptions {
+        selection: Policy::Shuffle,
+        ..Default::default()
+    };
+    let seen: BTreeSet<String> = (0..TEXTS.len())
+        .map(|_| {
+            select_fortune_from(&ctx, &[&source], &options)
+                .unwrap()
+                .fortune
+                .text
+        })
+        .collect();
+    assert_eq!(seen.len(), TEXTS.len());
+    assert!(sandbox.join("cache").join("shuffle-bag.json").exists());
+}
+
+/// Sorgente che conta le citazioni lette
+struct CountingSource {
+    inner: MemorySource,
+    reads: Cell<usize>,
+}
+
+impl FortuneSource for CountingSource {
+    fn metadata(&self) -> &SourceMetadata {
+        self.inner.metadata()
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn get(&self, index: usize) -> rfortune::Result<Option<String>> {
+        self.reads.set(self.reads.get() + 1);
+        self.inner.get(index)
+    }
+}
+
+#[test]
+fn test_selection_reads_only_the_needed_quotes() {
+    let source = CountingSource {
+        inner: MemorySource::new("db", TEXTS.iter().map(|t| t.to_string()).collect()),
+        reads: Cell::new(0),
+    };
+    let sources: [&dyn FortuneSource; 1] = [&source];
+
+    // Senza filtri sul testo si legge solo la citazione scelta
+    select_fortune_with(&sources, &mut Uniform).unwrap();
+    assert_eq!(source.reads.get(), 1);
+
+    // Con un filtro di lunghezza ogni candidata viene letta una volta, più la scelta
+    source.reads.set(0);
+    let mut short = Uniform.with_filter(|c: &Candidate<'_>| c.text().unwrap().len() <= 4);
+    let selection = select_fortune_with(&sources, &mut short).unwrap();
+    assert!(selection.fortune.text.len() <= 4);
+    assert_eq!(source.reads.get(), TEXTS.len() + 1);
+}
@@ -0,0 +1,339 @@
+mod common;
+
+use common::sandbox;
+use rfortune::Error;
+use rfortune::context::{AppContext, Paths};
+use rfortune::encoding::TextEncoding;
+use rfortune::loader::{FortuneFile, ParseOptions};
+use rfortune::source::{
+    DirectorySource, FortuneSource, MappedSource, MemorySource, StrfileSource, StructuredSource,
+    open_source, open_source_with,
+};
+use rfortune::utils::{PrintOptions, select_fortune, select_fortune_from};
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+/// Indice `strfile` (versione 2) per le citazioni che iniziano agli offset indicati
+fn strfile_index(offsets: &[u32], end: u32, flags: u32) -> Vec<u8> {
+    let mut data = Vec::new();
+    for word in [2, offsets.len() as u32, 0, 0, flags] {
+        data.extend_from_slice(&word.to_be_bytes());
+    }
+    data.extend_from_slice(&[b'%', 0, 0, 0]);
+    for offset in offsets.iter().chain([&end]) {
+        data.extend_from_slice(&offset.to_be_bytes());
+    }
+    data
+}
+
+fn collect(source: &dyn FortuneSource) -> Vec<String> {
+    source.iter().map(Result::unwrap).collect()
+}
+
+#[test]
+fn test_memory_source() {
+    let source = MemorySource::new("db:quotes", vec!["One".into(), "Two".into()]).with_title("DB");
+
+    assert_eq!(source.len(), 2);
+    assert_eq!(source.get(1).unwrap().as_deref(), Some("Two"));
+    assert_eq!(source.get(2).unwrap(), None);
+    assert_eq!(source.metadata().id, "db:quotes");
+    assert_eq!(source.metadata().title.as_deref(), Some("DB"));
+    assert_eq!(collect(&source), ["One", "Two"]);
+}
+
+#[test]
+fn test_select_fortune_from_custom_source() {
+    let sandbox = sandbox("custom");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+    let source = MemorySource::new("db", vec!["Only quote".into()]).with_title("Database");
+
+    let selection = select_fortune_from(&ctx, &[&source], &PrintOptions::default()).unwrap();
+    assert_eq!(selection.fortune.text, "Only quote");
+    assert_eq!(selection.fortune.title.as_deref(), Some("Database"));
+    assert_eq!(selection.fortune.source, Path::new("db"));
+    assert_eq!(selection.fortune.index, 1);
+
+    let empty = MemorySource::new("empty", vec![]);
+    assert!(matches!(
+        select_fortune_from(&ctx, &[&empty], &PrintOptions::default()),
+        Err(Error::NoQuotes { path: None })
+    ));
+}
+
+#[test]
+fn test_strfile_source() {
+    let sandbox = sandbox("strfile");
+    let text = "First quote.\n%\nSecond\nquote.\n%\nThird.\n%\n";
+    let path = sandbox.join("fortunes");
+    fs::write(&path, text).unwrap();
+    // Indice in ordine "casuale": le posizioni seguono l'indice, non il file
+    fs::write(
+        StrfileSource::index_path(&path),
+        strfile_index(&[31, 0, 15], text.len() as u32, 0x1),
+    )
+    .unwrap();
+
+    let source = open_source(&path).unwrap();
+    assert_eq!(source.len(), 3);
+    assert_eq!(
+        collect(source.as_ref()),
+        ["Third.", "First quote.", "Second\nquote."]
+    );
+}
+
+#[test]
+fn test_strfile_rotated() {
+    let sandbox = sandbox("rot13");
+    let text = "Uryyb, jbeyq!\n%\n";
+    let path = sandbox.join("offensive");
+    fs::write(&path, text).unwrap();
+    fs::write(
+        StrfileSource::index_path(&path),
+        strfile_index(&[0], text.len() as u32, 0x4),
+    )
+    .unwrap();
+
+    let source = StrfileSource::open(&path).unwrap();
+    assert_eq!(source.get(0).unwrap().as_deref(), Some("Hello, world!"));
+}
+
+#[test]
+fn test_strfile_title() {
+    let sandbox = sandbox("strfile_title");
+    let text = "# Classics\n%\nFirst.\n%\n";
+    let path = sandbox.join("classics");
+    fs::write(&path, text).unwrap();
+    fs::write(
+        StrfileSource::index_path(&path),
+        strfile_index(&[0, 13], text.len() as u32, 0),
+    )
+    .unwrap();
+
+    let source = StrfileSource::open(&path).unwrap();
+    assert_eq!(source.metadata().title.as_deref(), Some("Classics"));
+    assert_eq!(collect(&source), ["First."]);
+
+    // Titolo e prima citazione senza delimitatore in mezzo
+    let text = "# Classics\nFirst.\n%\nSecond.\n%\n";
+    fs::write(&path, text).unwrap();
+    fs::write(
+        StrfileSource::index_path(&path),
+        strfile_index(&[0, 20], text.len() as u32, 0),
+    )
+    .unwrap();
+    let source = StrfileSource::open(&path).unwrap();
+    assert_eq!(source.metadata().title.as_deref(), Some("Classics"));
+    assert_eq!(collect(&source), ["First.", "Second."]);
+}
+
+#[test]
+fn test_strfile_truncated_index_is_ignored() {
+    let sandbox = sandbox("strfile_truncated");
+    let path = sandbox.join("fortunes");
+    fs::write(&path, "One.\n%\nTwo.\n%\n").unwrap();
+    let index = strfile_index(&[0, 7], 13, 0);
+    fs::write(StrfileSource::index_path(&path), &index[..index.len() - 8]).unwrap();
+
+    assert!(!StrfileSource::has_index(&path));
+    fs::write(StrfileSource::index_path(&path), &index).unwrap();
+    assert!(StrfileSource::has_index(&path));
+}
+
+#[test]
+fn test_structured_sources() {
+    let sandbox = sandbox("structured");
+    let json = sandbox.join("quotes.json");
+    fs::write(&json, r#"{ "title": "JSON", "quotes": ["A", "B"] }"#).unwrap();
+    let yaml = sandbox.join("quotes.yaml");
+    fs::write(&yaml, "- C\n- |\n  D\n  E\n").unwrap();
+
+    let source = StructuredSource::open(&json).unwrap();
+    assert_eq!(source.metadata().title.as_deref(), Some("JSON"));
+    assert_eq!(collect(&source), ["A", "B"]);
+
+    let source = open_source(&yaml).unwrap();
+    assert_eq!(collect(source.as_ref()), ["C", "D\nE"]);
+
+    let broken = sandbox.join("broken.json");
+    fs::write(&broken, "{\n  \"quotes\": [1,\n").unwrap();
+    assert!(matches!(
+        StructuredSource::open(&broken),
+        Err(Error::Parse { .. })
+    ));
+}
+
+#[test]
+fn test_directory_source() {
+    let sandbox = sandbox("directory");
+    fs::write(sandbox.join("a.fort"), "# A\n%\nA1\n%\nA2\n%").unwrap();
+    fs::write(sandbox.join("b.json"), r#"["B1"]"#).unwrap();
+    fs::write(sandbox.join("empty.fort"), "").unwrap();
+    fs::write(sandbox.join(".hidden"), "%\nHidden\n%").unwrap();
+
+    let dir = DirectorySource::open(&sandbox).unwrap();
+    assert_eq!(dir.len(), 3);
+    assert_eq!(collect(&dir), ["A1", "A2", "B1"]);
+    assert_eq!(dir.skipped().len(), 1);
+
+    // La provenienza è il file che contiene la citazione
+    let (meta, index) = dir.origin(2);
+    assert_eq!(meta.path.as_deref(), Some(sandbox.join("b.json").as_path()));
+    assert_eq!(index, 0);
+    let (meta, index) = dir.origin(1);
+    assert_eq!(meta.title.as_deref(), Some("A"));
+    assert_eq!(index, 1);
+}
+
+#[test]
+fn test_select_fortune_from_directory() {
+    let sandbox = sandbox("select_dir");
+    let dir = sandbox.join("fortunes");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("only.fort"), "# Only\n%\nThe one\n%").unwrap();
+    fs::write(dir.join("empty.fort"), "").unwrap();
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+
+    let selection = select_fortune(&ctx, &[dir.as_path()], &PrintOptions::default()).unwrap();
+    assert_eq!(selection.fortune.text, "The one");
+    assert_eq!(selection.fortune.source, dir.join("only.fort"));
+    assert_eq!(selection.fortune.title.as_deref(), Some("Only"));
+    assert_eq!(selection.warnings.len(), 1);
+}
+
+#[test]
+fn test_mapped_source_matches_loader() {
+    let sandbox = sandbox("mapped");
+    let data = "\n# Title after a blank line\n%\n  First\r\n  quote \r\n %  \n\n%\nSecond\n%\n%\n   \nLast, no newline";
+    let path = sandbox.join("tricky.fort");
+    fs::write(&path, data).unwrap();
+
+    let mapped = MappedSource::open(&path).unwrap();
+    let loaded = FortuneFile::from_file(&path).unwrap();
+    assert_eq!(mapped.metadata().title, loaded.title);
+    assert_eq!(
+        mapped.metadata().title.as_deref(),
+        Some("Title after a blank line")
+    );
+    assert_eq!(collect(&mapped), loaded.quotes);
+    assert_eq!(
+        loaded.quotes,
+        ["First\n  quote", "Second", "Last, no newline"]
+    );
+}
+
+#[test]
+fn test_mapped_source_lends_quotes() {
+    let sandbox = sandbox("mapped_borrow");
+    let path = sandbox.join("plain.fort");
+    fs::write(&path, "%\nOne\ntwo\n%\nThree\n%\n").unwrap();
+
+    // Il testo viene letto dal file mappato, senza copie
+    let source = open_source(&path).unwrap();
+    assert!(matches!(
+        source.quote(0).unwrap(),
+        Some(Cow::Borrowed("One\ntwo"))
+    ));
+    assert_eq!(source.quote(2).unwrap(), None);
+
+    let empty = sandbox.join("empty.fort");
+    fs::write(&empty, "").unwrap();
+    assert!(matches!(
+        MappedSource::open(&empty),
+        Err(Error::NoQuotes { .. })
+    ));
+
+    // Con il BOM UTF-8 il testo resta nel file mappato
+    let bom = sandbox.join("bom.fort");
+    fs::write(&bom, b"\xEF\xBB\xBF# Title\n%\nOne\n%\n").unwrap();
+    let source = MappedSource::open(&bom).unwrap();
+    assert_eq!(source.metadata().title.as_deref(), Some("Title"));
+    assert!(matches!(
+        source.quote(0).unwrap(),
+        Some(Cow::Borrowed("One"))
+    ));
+}
+
+#[test]
+fn test_legacy_encodings_are_decoded() {
+    let sandbox = sandbox("encodings");
+    let latin1 = sandbox.join("latin1.fort");
+    fs::write(&latin1, b"%\nok\n%\nCaf\xe9\n").unwrap();
+    let source = MappedSource::open(&latin1).unwrap();
+    assert_eq!(collect(&source), ["ok", "Café"]);
+
+    let options = ParseOptions {
+        encoding: TextEncoding::Utf8,
+        ..Default::default()
+    };
+    assert!(matches!(
+        MappedSource::open_with(&latin1, &options),
+        Err(Error::Parse { line: Some(4), .. })
+    ));
+
+    // Anche le raccolte con indice `strfile`
+    let text = b"Na\xefve.\n%\n";
+    let path = sandbox.join("legacy");
+    fs::write(&path, text).unwrap();
+    fs::write(
+        StrfileSource::index_path(&path),
+        strfile_index(&[0], text.len() as u32, 0),
+    )
+    .unwrap();
+    assert_eq!(collect(open_source(&path).unwrap().as_ref()), ["Naïve."]);
+}
+
+#[test]
+fn test_mapped_source_with_parse_options() {
+    let sandbox = sandbox("mapped_options");
+    let path = sandbox.join("options.fort");
+    fs::write(&path, "# T\n%%\nPlain\n%%\nWith\n# comment\n\\%%\n%%\n").unwrap();
+    let options = ParseOptions {
+        delimiter: "%%".parse().unwrap(),
+        comments: true,
+        ..Default::default()
+    };
+
+    let mapped = MappedSource::open_with(&path, &options).unwrap();
+    let loaded = FortuneFile::from_file_with(&path, &options).unwrap();
+    assert_eq!(collect(&mapped), loaded.quotes);
+    assert_eq!(loaded.quotes, ["Plain", "With\n%%"]);
+
+    // Solo le citazioni con commenti o escape vengono copiate
+    assert!(matches!(
+        mapped.quote(0).unwrap(),
+        Some(Cow::Borrowed("Plain"))
+    ));
+    assert!(matches!(mapped.quote(1).unwrap(), Some(Cow::Owned(_))));
+
+    let dir = open_source_with(&sandbox, &options).unwrap();
+    assert_eq!(collect(dir.as_ref()), loaded.quotes);
+}
+
+#[test]
+fn test_mapped_source_preserves_whitespace() {
+    let sandbox = sandbox("mapped_whitespace");
+    let path = sandbox.join("art.fort");
+    fs::write(
+        &path,
+        "%\r\n    /\\_/\\\r\n   ( o.o )\r\n    > ^ <\r\n%\n  indented\n\n%\n",
+    )
+    .unwrap();
+    let options = ParseOptions {
+        preserve_whitespace: true,
+        ..Default::default()
+    };
+
+    let mapped = MappedSource::open_with(&path, &options).unwrap();
+    let loaded = FortuneFile::from_file_with(&path, &options).unwrap();
+    assert_eq!(collect(&mapped), loaded.quotes);
+    assert_eq!(
+        loaded.quotes,
+        ["    /\\_/\\\n   ( o.o )\n    > ^ <", "  indented\n"]
+    );
+    assert!(matches!(
+        mapped.quote(1).unwrap(),
+        Some(Cow::Borrowed("  indented\n"))
+    ));
+}
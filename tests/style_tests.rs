@@ -0,0 +1,102 @@
+use rfortune::style::{Character, Style, render};
+use rfortune::wrap::{WrapOptions, display_width};
+
+fn width(w: usize) -> WrapOptions {
+    WrapOptions {
+        width: Some(w),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_plain_style_is_unchanged() {
+    let out = render(
+        "Hello world",
+        Some("Title"),
+        Style::Plain,
+        Character::Cow,
+        &WrapOptions::default(),
+    );
+    assert_eq!(out, "Hello world");
+}
+
+#[test]
+fn test_unicode_box_with_title() {
+    let out = render(
+        "Hello",
+        Some("Tips"),
+        Style::Box,
+        Character::Cow,
+        &WrapOptions::default(),
+    );
+    assert_eq!(out, "┌─ Tips ─┐\n│ Hello  │\n└────────┘");
+}
+
+#[test]
+fn test_ascii_box_respects_width() {
+    let text = "Anything that can go wrong will go wrong, and at the worst possible moment.";
+    let out = render(text, None, Style::Ascii, Character::Cow, &width(30));
+
+    let lines: Vec<&str> = out.lines().collect();
+    assert!(lines.len() > 3);
+    assert!(lines.iter().all(|l| display_width(l) <= 30));
+    assert!(lines[0].starts_with("+-") && lines[0].ends_with('+'));
+    // Tutte le righe della cornice hanno la stessa larghezza
+    assert!(
+        lines
+            .iter()
+            .all(|l| display_width(l) == display_width(lines[0]))
+    );
+}
+
+#[test]
+fn test_box_with_wide_characters() {
+    let out = render(
+        "七転び八起き",
+        None,
+        Style::Box,
+        Character::Cow,
+        &WrapOptions::default(),
+    );
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[1], "│ 七転び八起き │");
+    assert!(
+        lines
+            .iter()
+            .all(|l| display_width(l) == display_width(lines[0]))
+    );
+}
+
+#[test]
+fn test_bubble_single_and_multi_line() {
+    let out = render(
+        "Moo.",
+        None,
+        Style::Bubble,
+        Character::Cow,
+        &WrapOptions::default(),
+    );
+    assert!(out.starts_with(" ______\n< Moo. >\n ------\n"));
+    assert!(out.contains("(oo)"));
+
+    let out = render(
+        "one\ntwo\nthree",
+        None,
+        Style::Bubble,
+        Character::Tux,
+        &WrapOptions::default(),
+    );
+    assert!(out.contains("/ one   \\\n| two   |\n\\ three /"));
+    assert!(out.contains("|o_o |"));
+}
+
+#[test]
+fn test_style_and_character_parsing() {
+    assert_eq!("box".parse::<Style>().unwrap(), Style::Box);
+    assert_eq!("ASCII".parse::<Style>().unwrap(), Style::Ascii);
+    assert_eq!("cowsay".parse::<Style>().unwrap(), Style::Bubble);
+    assert!("fancy".parse::<Style>().is_err());
+
+    assert_eq!("ferris".parse::<Character>().unwrap(), Character::Ferris);
+    assert!("dragon".parse::<Character>().is_err());
+}
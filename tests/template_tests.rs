@@ -0,0 +1,60 @@
+use rfortune::template::{Template, TemplateContext, split_attribution};
+use std::path::Path;
+
+fn sample_context() -> TemplateContext {
+    TemplateContext::new("Premature optimization is the root of all evil.\n— Donald Knuth")
+        .with_title(Some("Programming".to_string()))
+        .with_source(Path::new("dev.fort"))
+        .with_index(2)
+}
+
+#[test]
+fn test_split_attribution() {
+    let (text, author) = split_attribution("Fortune favors the bold.\n— Publius Vergilius Maro");
+    assert_eq!(text, "Fortune favors the bold.");
+    assert_eq!(author.as_deref(), Some("Publius Vergilius Maro"));
+
+    let (text, author) = split_attribution("In Rust we trust.");
+    assert_eq!(text, "In Rust we trust.");
+    assert!(author.is_none());
+}
+
+#[test]
+fn test_template_placeholders() {
+    let tpl = Template::parse("[{title} #{index}] {text} ({author}) <{source}>").unwrap();
+    assert_eq!(
+        tpl.render(&sample_context()),
+        "[Programming #2] Premature optimization is the root of all evil. (Donald Knuth) <dev.fort>"
+    );
+}
+
+#[test]
+fn test_template_conditionals() {
+    let tpl =
+        Template::parse("{text}{?author}\\n  -- {author}{/author}{!author} (anonymous){/author}")
+            .unwrap();
+
+    assert_eq!(
+        tpl.render(&sample_context()),
+        "Premature optimization is the root of all evil.\n  -- Donald Knuth"
+    );
+    assert_eq!(
+        tpl.render(&TemplateContext::new("In Rust we trust.")),
+        "In Rust we trust. (anonymous)"
+    );
+}
+
+#[test]
+fn test_template_literal_braces() {
+    let tpl = Template::parse("{{{index}}}").unwrap();
+    assert_eq!(tpl.render(&sample_context()), "{2}");
+}
+
+#[test]
+fn test_template_errors() {
+    assert!(Template::parse("{unknown}").is_err());
+    assert!(Template::parse("{text").is_err());
+    assert!(Template::parse("{?author}{text}").is_err());
+    assert!(Template::parse("{?author}{/title}").is_err());
+    assert!(Template::parse("text}").is_err());
+}
@@ -1,24 +1,20 @@
-use rfortune::config::{get_config_path, load_config, set_app_dir_for_tests};
+mod common;
+
+use common::sandbox;
+use rfortune::Error;
+use rfortune::config::load_config;
+use rfortune::context::{AppContext, Paths};
+use rfortune::loader::{Delimiter, FortuneFile, ParseOptions};
+use rfortune::selector::{Candidate, Policy};
+use rfortune::source::{FortuneSource, STDIN_ID, SourceMetadata, TextSource};
 use rfortune::utils::{
-    load_last_cache, print_random_from_files, random_nonrepeating, save_last_cache,
+    InitMode, InitStatus, PrintOptions, WaitOptions, ensure_app_initialized, load_last_cache,
+    load_last_cache_with, random_nonrepeating, random_quote, render_fortune, save_last_cache,
+    select_fortune, select_fortune_from, source_line,
 };
 use std::fs;
-use std::path::{Path, PathBuf};
-
-fn setup_test_env() -> PathBuf {
-    let nanos = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    let sandbox = std::env::temp_dir().join(format!(
-        "rfortune_test_env_utils_{}_{}",
-        std::process::id(),
-        nanos
-    ));
-    fs::create_dir_all(&sandbox).unwrap();
-    set_app_dir_for_tests(sandbox.clone());
-    sandbox
-}
+use std::path::Path;
+use std::time::Duration;
 
 #[test]
 fn test_random_quote_selection() {
@@ -28,26 +24,73 @@ fn test_random_quote_selection() {
         String::from("Quote C"),
     ];
 
-    let result = random_nonrepeating(&quotes, None);
+    let result = random_nonrepeating(&quotes, None).unwrap();
     assert!(quotes.contains(&result.to_string()));
+    assert_eq!(
+        random_nonrepeating(&quotes[..1], Some("Quote A".into())),
+        Some("Quote A")
+    );
+
+    // Una lista vuota non fa panico
+    assert_eq!(random_nonrepeating(&[], None), None);
+    assert_eq!(random_quote(&[]), None);
 }
 
 #[test]
-fn test_print_random_output() {
-    let sandbox = setup_test_env();
+fn test_select_fortune_returns_quote_and_origin() {
+    let sandbox = sandbox("select_fortune_returns_quote_and_origin");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
 
     let file_path = sandbox.join("test_single.fort");
-    fs::write(&file_path, "Hello world\n%").expect("Failed to write temp fortune file");
+    fs::write(&file_path, "# Greetings\nHello world\n%")
+        .expect("Failed to write temp fortune file");
 
     let paths: Vec<&Path> = vec![file_path.as_path()];
 
-    let result = print_random_from_files(&paths);
-    assert!(result.is_ok());
+    let selection = select_fortune(&ctx, &paths, &PrintOptions::default()).unwrap();
+    assert_eq!(selection.fortune.text, "Hello world");
+    assert_eq!(selection.fortune.title.as_deref(), Some("Greetings"));
+    assert_eq!(selection.fortune.source, file_path);
+    assert_eq!(selection.fortune.index, 1);
+    assert!(selection.warnings.is_empty());
+
+    let options = PrintOptions {
+        show_source: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        render_fortune(&selection.fortune, &options),
+        format!("{}\n%\nHello world", source_line(&file_path, 1))
+    );
+}
+
+#[test]
+fn test_unreadable_files_become_warnings() {
+    let sandbox = sandbox("unreadable_files_become_warnings");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+
+    let good = sandbox.join("good.fort");
+    let missing = sandbox.join("missing.fort");
+    fs::write(&good, "Only quote\n").unwrap();
+    let paths: Vec<&Path> = vec![missing.as_path(), good.as_path()];
+
+    let selection = select_fortune(&ctx, &paths, &PrintOptions::default()).unwrap();
+    assert_eq!(selection.fortune.text, "Only quote");
+    assert_eq!(selection.warnings.len(), 1);
+    assert!(selection.warnings[0].message.contains("missing.fort"));
+
+    // Nessun file utilizzabile: l'errore è quello del file
+    let paths: Vec<&Path> = vec![missing.as_path()];
+    assert!(matches!(
+        select_fortune(&ctx, &paths, &PrintOptions::default()),
+        Err(Error::Io { .. })
+    ));
 }
 
 #[test]
 fn test_cache_read_write() {
-    let sandbox = setup_test_env();
+    let sandbox = sandbox("cache_read_write");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
 
     let file_path = sandbox.join("test_cache_source.fort");
     let quote = "Hello Cache";
@@ -55,18 +98,24 @@ fn test_cache_read_write() {
     // Ensure the source file exists so canonicalization produces stable absolute path
     fs::write(&file_path, "Sample content\n%").expect("write source file");
 
-    save_last_cache(&file_path, quote).expect("failed to save cache");
+    save_last_cache(&ctx, &file_path, 0, quote).expect("failed to save cache");
 
-    let loaded = load_last_cache(&file_path).expect("failed to load cache");
+    let loaded = load_last_cache(&ctx, &file_path)
+        .expect("failed to load cache")
+        .expect("cache missing");
 
-    assert_eq!(loaded, quote);
+    assert_eq!(loaded.text, quote);
+    let id = file_path.to_string_lossy();
+    assert!(loaded.is(&Candidate::new(&id, 0, quote)));
+    assert!(!loaded.is(&Candidate::new(&id, 0, "Edited quote")));
 }
 
 #[test]
 fn test_config_auto_migration_to_fortune_files() {
-    let _sandbox = setup_test_env(); // ✅ forza app_dir() → SANDBOX
+    let sandbox = sandbox("config_auto_migration_to_fortune_files");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox)); // ✅ tutte le directory dell'app nella SANDBOX
 
-    let cfg_path = get_config_path();
+    let cfg_path = ctx.paths.config_file();
     let parent = cfg_path.parent().unwrap();
     fs::create_dir_all(parent).unwrap();
 
@@ -76,14 +125,15 @@ use_cache: true
 "#;
     fs::write(&cfg_path, legacy_config).unwrap();
 
-    let cfg = load_config().expect("config must load");
+    let cfg = load_config(&ctx).expect("config must load");
 
     assert_eq!(cfg.fortune_files, vec!["test.fort".to_string()]);
 }
 
 #[test]
 fn test_no_repeat_on_same_file() {
-    let sandbox = setup_test_env();
+    let sandbox = sandbox("no_repeat_on_same_file");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
 
     let file_path = sandbox.join("test_no_repeat.fort");
     let content = "Quote 1\n%\nQuote 2\n%\nQuote 3\n";
@@ -91,10 +141,302 @@ fn test_no_repeat_on_same_file() {
 
     let paths: Vec<&Path> = vec![file_path.as_path()];
 
-    save_last_cache(&file_path, "Quote 1").expect("failed to save initial cache");
+    save_last_cache(&ctx, &file_path, 0, "Quote 1").expect("failed to save initial cache");
+
+    select_fortune(&ctx, &paths, &PrintOptions::default()).expect("select_fortune failed");
+
+    let new_last = load_last_cache(&ctx, &file_path)
+        .expect("failed to load cache")
+        .expect("cache missing after print");
+    assert_ne!(new_last.text, "Quote 1");
+    assert_ne!(new_last.id.index, 0);
+}
+
+#[test]
+fn test_wait_duration_is_proportional_and_clamped() {
+    let wait = WaitOptions {
+        chars_per_second: 10.0,
+        min: Duration::from_secs(2),
+        max: Duration::from_secs(5),
+    };
+
+    assert_eq!(wait.duration_for("short"), Duration::from_secs(2));
+    assert_eq!(wait.duration_for(&"x".repeat(40)), Duration::from_secs(4));
+    assert_eq!(wait.duration_for(&"x".repeat(500)), Duration::from_secs(5));
+}
+
+#[test]
+fn test_wait_duration_with_tiny_speed_is_capped() {
+    let wait = WaitOptions {
+        chars_per_second: 1e-30,
+        ..Default::default()
+    };
+
+    assert_eq!(wait.duration_for("Any quote"), wait.max);
+}
+
+#[test]
+fn test_show_source_prints_origin_and_position() {
+    let sandbox = sandbox("show_source_prints_origin_and_position");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+    let path = sandbox.join("dev.fort");
+    fs::write(&path, "# Dev\n%\nA\n%\nLong enough\n%\nB\n%\n").unwrap();
+
+    // Il filtro di lunghezza lascia solo la seconda citazione del file
+    let options = PrintOptions {
+        show_source: true,
+        min_length: Some(5),
+        use_cache: false,
+        ..Default::default()
+    };
+    let selection = select_fortune(&ctx, &[path.as_path()], &options).unwrap();
+
+    assert_eq!(
+        render_fortune(&selection.fortune, &options),
+        format!("{}\n%\nLong enough", source_line(&path, 2))
+    );
+    assert_eq!(source_line(&path, 2), format!("({} #2)", path.display()));
+}
+
+#[test]
+fn test_preserved_quotes_are_not_reflowed() {
+    let sandbox = sandbox("preserved_quotes_are_not_reflowed");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+    let path = sandbox.join("art.fort");
+    let art = "  /\\_/\\   a cat wider than the width\n ( o.o )\n  > ^ <";
+    fs::write(&path, format!("#: preserve_whitespace = on\n{art}\n%\n")).unwrap();
+
+    let mut options = PrintOptions {
+        use_cache: false,
+        ..Default::default()
+    };
+    options.wrap.width = Some(12);
+    options.wrap.indent = 2;
+    let selection = select_fortune(&ctx, &[path.as_path()], &options).unwrap();
+    assert!(selection.fortune.preformatted);
+
+    let expected: Vec<String> = art.lines().map(|l| format!("  {l}")).collect();
+    assert_eq!(
+        render_fortune(&selection.fortune, &options),
+        expected.join("\n")
+    );
+}
+
+#[test]
+fn test_length_filters_and_weights() {
+    let sandbox = sandbox("length_filters_and_weights");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+
+    let short = sandbox.join("short.fort");
+    let long = sandbox.join("long.fort");
+    fs::write(&short, "Tiny\n%\nSmall\n").unwrap();
+    fs::write(&long, "A much longer quote that goes on and on\n").unwrap();
+    let paths: Vec<&Path> = vec![short.as_path(), long.as_path()];
+
+    // Solo la citazione lunga supera `min_length`
+    let options = PrintOptions {
+        min_length: Some(20),
+        ..Default::default()
+    };
+    select_fortune(&ctx, &paths, &options).unwrap();
+    assert!(load_last_cache(&ctx, &short).unwrap().is_none());
+    assert!(
+        load_last_cache(&ctx, &long)
+            .unwrap()
+            .unwrap()
+            .text
+            .starts_with("A much longer")
+    );
+
+    // Un peso nullo esclude il file
+    let mut options = PrintOptions::default();
+    options
+        .weights
+        .insert(long.to_string_lossy().to_string(), 0.0);
+    select_fortune(&ctx, &paths, &options).unwrap();
+    assert!(load_last_cache(&ctx, &short).unwrap().is_some());
+
+    // Nessuna citazione compatibile con i filtri
+    let options = PrintOptions {
+        max_length: Some(2),
+        ..Default::default()
+    };
+    assert!(matches!(
+        select_fortune(&ctx, &paths, &options),
+        Err(Error::NoMatchingQuotes)
+    ));
+}
+
+#[test]
+fn test_use_cache_false_leaves_cache_untouched() {
+    let sandbox = sandbox("use_cache_false_leaves_cache_untouched");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+
+    let file_path = sandbox.join("no_cache.fort");
+    fs::write(&file_path, "Only quote\n").unwrap();
+    let paths: Vec<&Path> = vec![file_path.as_path()];
+
+    let options = PrintOptions {
+        use_cache: false,
+        ..Default::default()
+    };
+    select_fortune(&ctx, &paths, &options).unwrap();
+    assert!(load_last_cache(&ctx, &file_path).unwrap().is_none());
+}
+
+#[test]
+fn test_init_modes_never_prompt_or_exit() {
+    let sandbox = sandbox("init_modes_never_prompt_or_exit").join("fresh");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+
+    // `--no-init`: nessun file creato
+    assert_eq!(
+        ensure_app_initialized(&ctx, InitMode::Never).unwrap(),
+        InitStatus::Skipped
+    );
+    assert!(!sandbox.exists());
+
+    // Senza `--yes` la libreria non chiede nulla: la conferma spetta al chiamante
+    assert_eq!(
+        ensure_app_initialized(&ctx, InitMode::Ask).unwrap(),
+        InitStatus::NeedsConfirmation
+    );
+    assert!(!sandbox.exists());
+
+    // `--yes`: inizializzazione senza domande
+    assert_eq!(
+        ensure_app_initialized(&ctx, InitMode::Yes).unwrap(),
+        InitStatus::Initialized
+    );
+    assert!(ctx.paths.config_file().exists());
+    assert!(ctx.paths.default_fortune_file().exists());
+
+    assert_eq!(
+        ensure_app_initialized(&ctx, InitMode::Never).unwrap(),
+        InitStatus::Ready
+    );
+}
+
+#[test]
+fn test_non_interactive_run_without_yes_creates_nothing() {
+    let sandbox = sandbox("non_interactive_run_without_yes_creates_nothing").join("fresh");
+
+    // `output()` non collega lo stdin a un terminale: non c'è nessuno a cui chiedere
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rfortune"))
+        .env("RFORTUNE_HOME", &sandbox)
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(78));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--yes"));
+    assert!(!sandbox.exists());
+}
+
+#[test]
+fn test_stdin_quotes_are_not_cached() {
+    let sandbox = sandbox("stdin_quotes_are_not_cached");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+    let file: FortuneFile = "one\n%\ntwo\n%\n".parse().unwrap();
+    let stdin = TextSource::new(SourceMetadata::new(STDIN_ID), file);
+    let sources: Vec<&dyn FortuneSource> = vec![&stdin];
+
+    for selection in [Policy::Uniform, Policy::Shuffle] {
+        let options = PrintOptions {
+            selection,
+            ..Default::default()
+        };
+        select_fortune_from(&ctx, &sources, &options).unwrap();
+    }
+
+    assert!(!ctx.paths.cache_file(Path::new(STDIN_ID)).exists());
+    let bag = fs::read_to_string(ctx.paths.cache_dir.join("shuffle-bag.json")).unwrap();
+    assert!(!bag.contains(STDIN_ID));
+}
+
+#[test]
+fn test_stdin_given_twice_is_rejected() {
+    let sandbox = sandbox("stdin_given_twice_is_rejected");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+    let paths = [Path::new("-"), Path::new("-")];
+
+    let err = select_fortune(&ctx, &paths, &PrintOptions::default()).unwrap_err();
+    assert!(matches!(err, Error::Config(_)), "{err}");
+}
+
+#[test]
+fn test_duplicate_quotes_keep_their_own_origin() {
+    let sandbox = sandbox("duplicate_quotes_keep_their_own_origin");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+
+    // Stessa citazione in due file con lo stesso nome, in directory diverse
+    let first = sandbox.join("a").join("dup.fort");
+    let second = sandbox.join("b").join("dup.fort");
+    fs::create_dir_all(first.parent().unwrap()).unwrap();
+    fs::create_dir_all(second.parent().unwrap()).unwrap();
+    fs::write(&first, "Unique to a\n%\nShared quote\n").unwrap();
+    fs::write(&second, "Shared quote\n").unwrap();
+
+    let mut options = PrintOptions::default();
+    options
+        .weights
+        .insert(first.to_string_lossy().to_string(), 0.0);
+    let paths: Vec<&Path> = vec![first.as_path(), second.as_path()];
+
+    let selection = select_fortune(&ctx, &paths, &options).unwrap();
+    assert_eq!(selection.fortune.source, second);
+    assert_eq!(selection.fortune.index, 1);
+    assert_eq!(selection.fortune.id.source_id, second.to_string_lossy());
+    assert_eq!(selection.fortune.id.index, 0);
+
+    // La cache appartiene al secondo file: il primo (stesso nome di cache) non la vede
+    let last = load_last_cache(&ctx, &second).unwrap().unwrap();
+    assert!(last.is(&Candidate::new(
+        &second.to_string_lossy(),
+        0,
+        "Shared quote"
+    )));
+    assert!(load_last_cache(&ctx, &first).unwrap().is_none());
+}
+
+#[test]
+fn test_legacy_text_cache_uses_the_active_parse_options() {
+    let sandbox = sandbox("legacy_text_cache_uses_the_active_parse_options");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+
+    let file_path = sandbox.join("custom.fort");
+    fs::write(&file_path, "Quote 1\n%%\nQuote 2\n").unwrap();
+    let store = ctx.paths.cache_file(&file_path);
+    fs::create_dir_all(store.parent().unwrap()).unwrap();
+    fs::write(&store, "Quote 2").unwrap();
 
-    print_random_from_files(&paths).expect("print_random_from_files failed");
+    // Con il separatore predefinito il file è una sola citazione e il testo non si ritrova
+    assert!(load_last_cache(&ctx, &file_path).unwrap().is_none());
+
+    let parse = ParseOptions {
+        delimiter: Delimiter::Line("%%".to_string()),
+        ..Default::default()
+    };
+    let last = load_last_cache_with(&ctx, &file_path, &parse)
+        .unwrap()
+        .unwrap();
+    assert!(last.is(&Candidate::new(&file_path.to_string_lossy(), 1, "Quote 2")));
+}
 
-    let new_last = load_last_cache(&file_path).expect("cache missing after print");
-    assert_ne!(new_last, "Quote 1");
+#[test]
+fn test_legacy_text_cache_is_still_read() {
+    let sandbox = sandbox("legacy_text_cache_is_still_read");
+    let ctx = AppContext::new(Paths::in_dir(&sandbox));
+
+    let file_path = sandbox.join("legacy.fort");
+    fs::write(&file_path, "Quote 1\n%\nQuote 2\n").unwrap();
+    let store = ctx.paths.cache_file(&file_path);
+    fs::create_dir_all(store.parent().unwrap()).unwrap();
+    fs::write(&store, "Quote 2").unwrap();
+
+    let last = load_last_cache(&ctx, &file_path).unwrap().unwrap();
+    assert!(last.is(&Candidate::new(&file_path.to_string_lossy(), 1, "Quote 2")));
+
+    let paths: Vec<&Path> = vec![file_path.as_path()];
+    let selection = select_fortune(&ctx, &paths, &PrintOptions::default()).unwrap();
+    assert_eq!(selection.fortune.text, "Quote 1");
 }
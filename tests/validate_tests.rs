@@ -0,0 +1,98 @@
+use rfortune::validate::{Severity, validate_content, validate_env};
+
+#[test]
+fn test_valid_content_has_no_issues() {
+    let content = "version: 1\nprint_title: true\nstyle: box\nfortune_files:\n  - a.fort\nwait_min_seconds: 3\n";
+    assert!(validate_content("rfortune.conf", content).is_empty());
+    assert!(validate_content("rfortune.conf", "").is_empty());
+}
+
+#[test]
+fn test_syntax_error_reports_line_and_column() {
+    let content = "print_title: true\nstyle: [box\n";
+    let issues = validate_content("rfortune.conf", content);
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Error);
+    assert!(issues[0].line.is_some());
+    assert!(issues[0].column.is_some());
+    assert!(issues[0].to_string().starts_with("rfortune.conf:"));
+}
+
+#[test]
+fn test_unknown_key_is_a_warning_with_suggestion() {
+    let issues = validate_content(
+        "rfortune.conf",
+        "use_cache: true\nprint_tilte: true\nversion: 1\n",
+    );
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Warning);
+    assert_eq!(issues[0].line, Some(2));
+    assert!(issues[0].message.contains("did you mean 'print_title'"));
+}
+
+#[test]
+fn test_wrong_types_are_errors() {
+    let content = "use_cache: \"yes\"\nfortune_files: single.fort\nstyle: fancy\nwait_max_seconds: -1\nversion: 1\n";
+    let issues = validate_content("rfortune.conf", content);
+
+    assert_eq!(issues.len(), 4);
+    assert!(issues.iter().all(|i| i.severity == Severity::Error));
+    let lines: Vec<Option<usize>> = issues.iter().map(|i| i.line).collect();
+    assert_eq!(lines, vec![Some(1), Some(2), Some(3), Some(4)]);
+    assert_eq!(
+        issues[0].to_string(),
+        "rfortune.conf:1:1: 'use_cache' expects a boolean (true/false), found the string \"yes\""
+    );
+}
+
+#[test]
+fn test_env_validation() {
+    let vars = vec![
+        ("RFORTUNE_USE_CACHE".to_string(), "maybe".to_string()),
+        ("RFORTUNE_STYLE".to_string(), "box".to_string()),
+        ("RFORTUNE_FORMAT".to_string(), "pdf".to_string()),
+        ("RFORTUNE_WAIT_MIN_SECONDS".to_string(), "abc".to_string()),
+        ("PATH".to_string(), "/usr/bin".to_string()),
+    ];
+
+    let issues = validate_env(vars);
+    let origins: Vec<&str> = issues.iter().map(|i| i.origin.as_str()).collect();
+    assert_eq!(
+        origins,
+        vec![
+            "RFORTUNE_USE_CACHE",
+            "RFORTUNE_FORMAT",
+            "RFORTUNE_WAIT_MIN_SECONDS"
+        ]
+    );
+}
+
+#[test]
+fn test_schema_version_checks() {
+    let old = validate_content("rfortune.conf", "print_title: true\n");
+    assert_eq!(old.len(), 1);
+    assert_eq!(old[0].severity, Severity::Info);
+    assert!(old[0].message.contains("config migrate"));
+
+    let newer = validate_content("rfortune.conf", "print_title: true\nversion: 99\n");
+    assert_eq!(newer.len(), 1);
+    assert_eq!(newer[0].severity, Severity::Error);
+    assert_eq!(newer[0].line, Some(2));
+
+    let bad = validate_content("rfortune.conf", "version: one\n");
+    assert!(bad[0].is_error());
+}
+
+#[test]
+fn test_profile_settings_are_validated() {
+    let content = "version: 1\nprofiles:\n  bot:\n    max_length: -1\n    stlye: box\n";
+    let issues = validate_content("rfortune.conf", content);
+
+    assert_eq!(issues.len(), 2);
+    assert!(issues[0].is_error());
+    assert_eq!((issues[0].line, issues[0].column), (Some(4), Some(5)));
+    assert_eq!(issues[1].severity, Severity::Warning);
+    assert!(issues[1].message.contains("did you mean 'style'"));
+}
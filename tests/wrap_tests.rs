@@ -0,0 +1,104 @@
+use rfortune::wrap::{WrapOptions, display_width, format_text, wrap_text};
+
+#[test]
+fn test_only_long_lines_are_wrapped() {
+    let text = "Anything that can go wrong will go wrong, and it will\ngo wrong at the worst possible moment.";
+    let lines = wrap_text(text, 40);
+
+    // La seconda riga ci sta: l'a capo dell'autore resta dov'è
+    assert_eq!(
+        lines,
+        vec![
+            "Anything that can go wrong will go",
+            "wrong, and it will",
+            "go wrong at the worst possible moment.",
+        ]
+    );
+}
+
+#[test]
+fn test_blank_lines_and_indentation_are_kept() {
+    let text = "\nA line the author broke on purpose, longer than forty columns\nhere.\n\n\n    indented words that do not fit";
+    assert_eq!(
+        wrap_text(text, 70),
+        vec![
+            "",
+            "A line the author broke on purpose, longer than forty columns",
+            "here.",
+            "",
+            "",
+            "    indented words that do not fit",
+        ]
+    );
+    assert_eq!(
+        wrap_text("    indented words that do not fit", 20),
+        vec!["    indented words", "    that do not fit"]
+    );
+}
+
+#[test]
+fn test_preserve_verse_and_attribution() {
+    let text = "Roses are red,\nViolets are blue,\nRust is fast,\nAnd so are you.\n— Anonymous";
+    let lines = wrap_text(text, 80);
+
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "Roses are red,");
+    assert_eq!(lines[4], "— Anonymous");
+}
+
+#[test]
+fn test_preserve_indented_code() {
+    let text = "fn main() {\n    println!(\"hi\");\n}";
+    let lines = wrap_text(text, 80);
+    assert_eq!(lines, vec!["fn main() {", "    println!(\"hi\");", "}"]);
+}
+
+#[test]
+fn test_paragraphs_are_kept_separate() {
+    let text = "First paragraph.\n\nSecond paragraph.";
+    assert_eq!(
+        wrap_text(text, 80),
+        vec!["First paragraph.", "", "Second paragraph."]
+    );
+}
+
+#[test]
+fn test_wide_characters() {
+    // Ogni ideogramma occupa due colonne
+    let text = "七転び八起き七転び八起き";
+    assert_eq!(display_width(text), 24);
+
+    let lines = wrap_text(text, 10);
+    assert!(lines.iter().all(|l| display_width(l) <= 10));
+    assert_eq!(lines.concat(), text);
+}
+
+#[test]
+fn test_combining_marks_stay_attached() {
+    let text = "e\u{301}e\u{301}e\u{301}e\u{301}";
+    let lines = wrap_text(text, 2);
+    assert_eq!(lines, vec!["e\u{301}e\u{301}", "e\u{301}e\u{301}"]);
+}
+
+#[test]
+fn test_indent_and_center() {
+    let opts = WrapOptions {
+        width: Some(20),
+        indent: 2,
+        center: true,
+    };
+    assert_eq!(format_text("abcd", &opts), "         abcd");
+
+    let opts = WrapOptions {
+        width: None,
+        indent: 4,
+        center: false,
+    };
+    assert_eq!(format_text("one\n\ntwo", &opts), "    one\n\n    two");
+}
+
+#[test]
+fn test_noop_keeps_text_verbatim() {
+    let text = "  keep   spacing  \n%";
+    assert_eq!(format_text(text, &WrapOptions::default()), text);
+}